@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::process::Command;
+use std::str::from_utf8;
+
+/// A parsed `cfg` predicate, like the condition in `#[cfg_attr(not(feature = "std"), no_std)]`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CfgExpr {
+    /// A name like `test`, `unix` or `debug_assertions`.
+    Name(String),
+    /// A key-value pair like `feature = "std"` or `target_os = "linux"`.
+    KeyValue(String, String),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+/// Configuration that `cfg` predicates are evaluated against.
+#[derive(Clone, Debug, Default)]
+pub struct Cfg {
+    names: HashSet<String>,
+    key_values: HashSet<(String, String)>,
+    /// Target triple, like `x86_64-unknown-linux-gnu`.
+    triple: Option<String>,
+}
+
+impl CfgExpr {
+    pub fn from_meta(meta: &syn::Meta) -> Option<Self> {
+        match meta {
+            syn::Meta::Path(path) => Some(CfgExpr::Name(path.get_ident()?.to_string())),
+            syn::Meta::NameValue(name_value) => match name_value.lit {
+                syn::Lit::Str(ref value) => Some(CfgExpr::KeyValue(
+                    name_value.path.get_ident()?.to_string(),
+                    value.value(),
+                )),
+                _ => None,
+            },
+            syn::Meta::List(list) => {
+                let mut predicates = vec![];
+                for nested in list.nested.iter() {
+                    match nested {
+                        syn::NestedMeta::Meta(meta) => predicates.push(CfgExpr::from_meta(meta)?),
+                        syn::NestedMeta::Lit(_) => return None,
+                    }
+                }
+                let operator = list.path.get_ident()?.to_string();
+                match operator.as_str() {
+                    "all" => Some(CfgExpr::All(predicates)),
+                    "any" => Some(CfgExpr::Any(predicates)),
+                    "not" if predicates.len() == 1 => {
+                        Some(CfgExpr::Not(Box::new(predicates.remove(0))))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Predicates of all `#[cfg(...)]` attributes in `attrs`.
+    pub fn from_cfg_attributes(attrs: &[syn::Attribute]) -> Vec<Self> {
+        attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("cfg"))
+            .filter_map(|attr| match attr.parse_meta() {
+                Ok(syn::Meta::List(ref list)) if list.nested.len() == 1 => {
+                    match list.nested.first() {
+                        Some(syn::NestedMeta::Meta(meta)) => CfgExpr::from_meta(meta),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn eval(&self, cfg: &Cfg) -> bool {
+        match self {
+            CfgExpr::Name(name) => cfg.names.contains(name),
+            CfgExpr::KeyValue(key, value) => {
+                cfg.key_values.contains(&(key.to_owned(), value.to_owned()))
+            }
+            CfgExpr::All(predicates) => predicates.iter().all(|n| n.eval(cfg)),
+            CfgExpr::Any(predicates) => predicates.iter().any(|n| n.eval(cfg)),
+            CfgExpr::Not(predicate) => !predicate.eval(cfg),
+        }
+    }
+
+    /// Whether the predicate can only hold in test builds, like `test` or
+    /// `all(test, feature = "std")`.
+    pub fn requires_test(&self) -> bool {
+        match self {
+            CfgExpr::Name(name) => name == "test",
+            CfgExpr::KeyValue(_, _) => false,
+            CfgExpr::All(predicates) => predicates.iter().any(|n| n.requires_test()),
+            CfgExpr::Any(predicates) => {
+                !predicates.is_empty() && predicates.iter().all(|n| n.requires_test())
+            }
+            CfgExpr::Not(_) => false,
+        }
+    }
+
+    /// All features that are referenced via `feature = "..."` in the predicate.
+    pub fn features(&self) -> Vec<String> {
+        let mut features = vec![];
+        self.collect_features(&mut features);
+        features.dedup();
+        features
+    }
+
+    fn collect_features(&self, features: &mut Vec<String>) {
+        match self {
+            CfgExpr::Name(_) => {}
+            CfgExpr::KeyValue(key, value) => {
+                if key == "feature" && !features.contains(value) {
+                    features.push(value.to_owned());
+                }
+            }
+            CfgExpr::All(predicates) | CfgExpr::Any(predicates) => {
+                for predicate in predicates {
+                    predicate.collect_features(features);
+                }
+            }
+            CfgExpr::Not(predicate) => predicate.collect_features(features),
+        }
+    }
+
+    /// Finds the smallest set of features that need to be deactivated in `cfg` for the predicate
+    /// to hold.
+    ///
+    /// Returns `None` if the predicate can not be satisfied by only deactivating features.
+    pub fn features_to_disable(&self, cfg: &Cfg) -> Option<Vec<String>> {
+        let active_features: Vec<String> = self
+            .features()
+            .into_iter()
+            .filter(|feature| cfg.has_feature(feature))
+            .collect();
+
+        // predicates only reference a handful of features, so trying all combinations ordered
+        // by size is cheap enough
+        let mut combinations: Vec<Vec<String>> = (0..1u64 << active_features.len())
+            .map(|mask| {
+                active_features
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, feature)| feature.to_owned())
+                    .collect()
+            })
+            .collect();
+        combinations.sort_by_key(|combination| combination.len());
+
+        combinations
+            .into_iter()
+            .find(|combination| self.eval(&cfg.without_features(combination)))
+    }
+}
+
+impl fmt::Display for CfgExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_list = |f: &mut fmt::Formatter, predicates: &[CfgExpr]| -> fmt::Result {
+            for (i, predicate) in predicates.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", predicate)?;
+            }
+            Ok(())
+        };
+        match self {
+            CfgExpr::Name(name) => write!(f, "{}", name),
+            CfgExpr::KeyValue(key, value) => write!(f, "{} = {:?}", key, value),
+            CfgExpr::All(predicates) => {
+                write!(f, "all(")?;
+                write_list(f, predicates)?;
+                write!(f, ")")
+            }
+            CfgExpr::Any(predicates) => {
+                write!(f, "any(")?;
+                write_list(f, predicates)?;
+                write!(f, ")")
+            }
+            CfgExpr::Not(predicate) => write!(f, "not({})", predicate),
+        }
+    }
+}
+
+impl Cfg {
+    /// Configuration of the host target, as reported by `rustc --print cfg`.
+    ///
+    /// `test` is never set, as the checked code is the one that ends up in a regular build.
+    pub fn host() -> Self {
+        let output = rustc(&["-vV"]).expect("failed to execute rustc");
+        let triple = output
+            .lines()
+            .find_map(|line| line.strip_prefix("host: "))
+            .map(|triple| triple.to_owned());
+        let mut cfg =
+            Cfg::from_print_cfg(&rustc(&["--print", "cfg"]).expect("failed to execute rustc"));
+        cfg.triple = triple;
+        cfg
+    }
+
+    /// Configuration of the target `triple`, as reported by `rustc --print cfg --target <triple>`.
+    ///
+    /// Returns `None` if rustc doesn't know the target.
+    pub fn target(triple: &str) -> Option<Self> {
+        let mut cfg = Cfg::from_print_cfg(&rustc(&["--print", "cfg", "--target", triple])?);
+        cfg.triple = Some(triple.to_owned());
+        Some(cfg)
+    }
+
+    fn from_print_cfg(output: &str) -> Self {
+        let mut cfg = Cfg::default();
+        for line in output.lines() {
+            match line.find('=') {
+                Some(separator) => {
+                    let key = line[..separator].to_owned();
+                    let value = line[separator + 1..].trim_matches('"').to_owned();
+                    cfg.key_values.insert((key, value));
+                }
+                None => {
+                    cfg.names.insert(line.to_owned());
+                }
+            }
+        }
+        cfg
+    }
+
+    /// Whether a dependency that is declared under `[target.<platform>.dependencies]` applies to
+    /// the target, where `platform` is either a target triple or a `cfg(...)` predicate.
+    ///
+    /// Predicates that can not be parsed are assumed to hold.
+    pub fn matches_platform(&self, platform: &str) -> bool {
+        if !platform.starts_with("cfg(") {
+            return self
+                .triple
+                .as_deref()
+                .is_none_or(|triple| triple == platform);
+        }
+        let predicate = match syn::parse_str::<syn::Meta>(platform) {
+            Ok(syn::Meta::List(ref list)) if list.nested.len() == 1 => match list.nested.first() {
+                Some(syn::NestedMeta::Meta(meta)) => CfgExpr::from_meta(meta),
+                _ => None,
+            },
+            _ => None,
+        };
+        predicate.is_none_or(|predicate| predicate.eval(self))
+    }
+
+    /// Target triple, like `x86_64-unknown-linux-gnu`.
+    pub fn triple(&self) -> Option<&str> {
+        self.triple.as_deref()
+    }
+
+    /// Configuration of a test build.
+    pub fn with_test(&self) -> Self {
+        let mut cfg = self.clone();
+        cfg.names.insert("test".to_owned());
+        cfg
+    }
+
+    pub fn with_features<'a, I: IntoIterator<Item = &'a str>>(&self, features: I) -> Self {
+        let mut cfg = self.clone();
+        for feature in features {
+            cfg.key_values
+                .insert(("feature".to_owned(), feature.to_owned()));
+        }
+        cfg
+    }
+
+    pub fn without_features(&self, features: &[String]) -> Self {
+        let mut cfg = self.clone();
+        for feature in features {
+            cfg.key_values
+                .remove(&("feature".to_owned(), feature.to_owned()));
+        }
+        cfg
+    }
+
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.key_values
+            .contains(&("feature".to_owned(), feature.to_owned()))
+    }
+}
+
+/// Stdout of a successful rustc invocation.
+fn rustc(args: &[&str]) -> Option<String> {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let output = Command::new(rustc).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(from_utf8(&output.stdout).ok()?.to_owned())
+}
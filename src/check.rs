@@ -1,107 +1,260 @@
-use proc_macro2;
-use proc_macro2::TokenTree;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 
+use crate::cfg::*;
 use crate::check_source::*;
 use crate::ext::*;
+use crate::lint::*;
+use crate::module_tree::*;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CrateSupport {
-    OnlyWithoutFeature(String),
+    /// Crate is only `no_std` if the condition of its `#![cfg_attr(<condition>, no_std)]`
+    /// attribute holds. The offenses are only relevant if they are still compiled in when the
+    /// condition holds.
+    ConditionalNoStd {
+        condition: CfgExpr,
+        offenses: Vec<SourceOffense>,
+    },
     /// proc macros are not actually linked, so they don't hinder no_std support
     ProcMacro,
     SourceOffenses(Vec<SourceOffense>),
     NoOffenseDetected,
+    /// The source of the crate can not be read or parsed, so it is unknown whether it supports
+    /// no_std.
+    Unchecked(SourceError),
 }
 
+/// The most capable standard library crate that a crate needs, ordered from least to most
+/// capable, so that the tier of a dependency tree is the maximum of the tiers of its crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SupportTier {
+    /// Only needs `core`.
+    Core,
+    /// Needs `alloc`, i.e. a global allocator has to be provided.
+    Alloc,
+    /// Needs `std`.
+    Std,
+}
+
+impl fmt::Display for SupportTier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SupportTier::Core => write!(f, "core"),
+            SupportTier::Alloc => write!(f, "alloc"),
+            SupportTier::Std => write!(f, "std"),
+        }
+    }
+}
+
+/// A `#[cfg_attr(<condition>, <attributes>...)]` attribute.
 #[derive(Debug)]
 pub struct ConditionalAttribute {
-    condition: proc_macro2::TokenStream,
-    pub attribute: syn::Ident,
+    pub condition: CfgExpr,
+    pub attributes: Vec<syn::Meta>,
 }
 
 impl ConditionalAttribute {
     pub fn from_attribute(attr: &syn::Attribute) -> Option<Self> {
-        let cfg_attr_path: syn::Path = syn::parse_quote!(cfg_attr);
-        if attr.path == cfg_attr_path {
-            if let Some(ref first_group_ts) = attr.clone().tokens.into_iter().next() {
-                // Group of the surrounding parenthesis
-                if let TokenTree::Group(group) = first_group_ts {
-                    let mut inner_group_stream = group.stream().into_iter();
-                    let condition_part_1 = inner_group_stream.next();
-                    let condition_part_2 = inner_group_stream.next();
-                    inner_group_stream.next();
-                    let gated_attr = inner_group_stream.next();
-
-                    if let Some(TokenTree::Ident(ref gated_attr_ident)) = gated_attr {
-                        let mut condition = proc_macro2::TokenStream::new();
-                        condition.extend(condition_part_1);
-                        condition.extend(condition_part_2);
-
-                        return Some(ConditionalAttribute {
-                            condition,
-                            attribute: gated_attr_ident.clone(),
-                        });
-                    }
-                }
-            }
-        }
-        return None;
-    }
-
-    pub fn required_feature(&self) -> Option<proc_macro2::Literal> {
-        let not_ident: syn::Ident = syn::parse_quote!(not);
-        let feature_ident: syn::Ident = syn::parse_quote!(feature);
-        let equal_punct: proc_macro2::Punct = syn::parse_quote!(=);
-
-        let mut ts = self.condition.clone().into_iter();
-        if let Some(TokenTree::Ident(not_ident_parsed)) = ts.next() {
-            if not_ident == not_ident_parsed {
-                if let Some(TokenTree::Group(group_parsed)) = ts.next() {
-                    let mut group_stream = group_parsed.stream().into_iter();
-                    let feat_ident = group_stream.next();
-                    let eq_punct = group_stream.next();
-                    let required_literal = group_stream.next();
-
-                    if let (
-                        Some(TokenTree::Ident(feat_ident_parsed)),
-                        Some(TokenTree::Punct(equal_punct_parsed)),
-                        Some(TokenTree::Literal(req_literal)),
-                    ) = (feat_ident, eq_punct, required_literal)
-                    {
-                        if feature_ident == feat_ident_parsed
-                            && equal_punct.as_char() == equal_punct_parsed.as_char()
-                        {
-                            return Some(req_literal);
-                        }
-                    }
-                }
-            }
+        if !attr.path.is_ident("cfg_attr") {
+            return None;
         }
-        return None;
+        let meta_list = match attr.parse_meta() {
+            Ok(syn::Meta::List(meta_list)) => meta_list,
+            _ => return None,
+        };
+        let mut nested = meta_list.nested.into_iter().map(|nested| match nested {
+            syn::NestedMeta::Meta(meta) => Some(meta),
+            syn::NestedMeta::Lit(_) => None,
+        });
+        let condition = CfgExpr::from_meta(&nested.next()??)?;
+        let attributes = nested.collect::<Option<Vec<_>>>()?;
+
+        Some(ConditionalAttribute {
+            condition,
+            attributes,
+        })
+    }
+
+    /// Whether one of the gated attributes is a plain `name` attribute like `no_std`.
+    pub fn contains_attribute(&self, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.path().is_ident(name))
     }
 }
 
 pub struct CheckResult {
+    pub package_id: String,
     pub package_name: String,
+    pub package_version: String,
+    /// Entry point of the crate whose source was checked, which is missing for proc macros.
+    pub src_path: Option<PathBuf>,
     pub support: CrateSupport,
     pub active_features: Vec<Feature>,
+    /// Configuration of the target the crate is checked for.
+    pub target_cfg: Cfg,
+    /// Whether test-only code is checked, in which case offenses are evaluated for a test build.
+    pub include_tests: bool,
+    pub alloc_usages: Vec<StdUsage>,
+    /// Whether the crate is the checked workspace member itself, instead of a dependency.
+    pub is_main_pkg: bool,
+    /// `name:version` of the crates that lead from the workspace member to the crate.
+    pub dependency_path: Vec<String>,
+    /// Workspace members that link the crate, if all members of a workspace are checked.
+    pub affected_members: Vec<String>,
+    /// Active features that would not be active with resolver 2.
+    pub resolver1_only_features: Vec<String>,
+    /// Levels of the lints for the crate.
+    pub lint_levels: HashMap<Lint, Level>,
 }
 
 impl CheckResult {
     pub fn no_std_itself(&self) -> bool {
         match self.support {
             CrateSupport::ProcMacro => true,
-            CrateSupport::OnlyWithoutFeature(ref feature) => !self.is_feature_active(feature),
+            CrateSupport::ConditionalNoStd { ref condition, .. } => {
+                condition.eval(&self.active_cfg()) && self.leaked_offenses().is_empty()
+            }
             CrateSupport::NoOffenseDetected => true,
-            CrateSupport::SourceOffenses(_) => false,
+            CrateSupport::SourceOffenses(_) => self.active_offenses().is_empty(),
+            CrateSupport::Unchecked(_) => false,
+        }
+    }
+
+    pub fn tier(&self) -> SupportTier {
+        if !self.no_std_itself() {
+            return SupportTier::Std;
         }
+        match self.active_alloc_usages().is_empty() {
+            true => SupportTier::Core,
+            false => SupportTier::Alloc,
+        }
+    }
+
+    /// Usages of `alloc` in code that is compiled for the active configuration.
+    pub fn active_alloc_usages(&self) -> Vec<&StdUsage> {
+        let active_cfg = self.offense_cfg();
+        self.alloc_usages
+            .iter()
+            .filter(|usage| usage.is_compiled_in(&active_cfg))
+            .collect()
+    }
+
+    /// Configuration that the offenses are evaluated against.
+    fn offense_cfg(&self) -> Cfg {
+        match self.include_tests {
+            true => self.active_cfg().with_test(),
+            false => self.active_cfg(),
+        }
+    }
+
+    /// Offenses in code that is compiled for the active configuration.
+    pub fn active_offenses(&self) -> Vec<&SourceOffense> {
+        match self.support {
+            CrateSupport::SourceOffenses(ref offenses) => {
+                let active_cfg = self.offense_cfg();
+                offenses
+                    .iter()
+                    .filter(|offense| offense.is_compiled_in(&active_cfg))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Offenses in code that is compiled out for the active configuration, e.g. because it is
+    /// gated behind `#[cfg(feature = "std")]` and the feature is not active.
+    pub fn compiled_out_offenses(&self) -> Vec<&SourceOffense> {
+        match self.support {
+            CrateSupport::SourceOffenses(ref offenses) => {
+                let active_cfg = self.offense_cfg();
+                offenses
+                    .iter()
+                    .filter(|offense| !offense.is_compiled_in(&active_cfg))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Configuration the crate is compiled with, i.e. the target configuration plus the active
+    /// features.
+    pub fn active_cfg(&self) -> Cfg {
+        self.target_cfg
+            .with_features(self.active_features.iter().map(|n| n.name.as_str()))
+    }
+
+    /// Minimal set of active features that need to be deactivated for a `ConditionalNoStd`
+    /// crate to be `no_std`.
+    pub fn features_to_disable(&self) -> Option<Vec<String>> {
+        match self.support {
+            CrateSupport::ConditionalNoStd { ref condition, .. } => {
+                condition.features_to_disable(&self.active_cfg())
+            }
+            _ => None,
+        }
+    }
+
+    /// Offenses of a `ConditionalNoStd` crate that are still compiled in after deactivating the
+    /// features that are required for no_std, i.e. usages of std that are not gated behind
+    /// those features.
+    pub fn leaked_offenses(&self) -> Vec<&SourceOffense> {
+        match self.support {
+            CrateSupport::ConditionalNoStd { ref offenses, .. } => {
+                let no_std_cfg = self
+                    .offense_cfg()
+                    .without_features(&self.features_to_disable().unwrap_or_default());
+                offenses
+                    .iter()
+                    .filter(|offense| offense.is_compiled_in(&no_std_cfg))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Lints of the findings that make the crate require std, in the order they are reported.
+    pub fn lints(&self) -> Vec<Lint> {
+        let mut lints = vec![];
+        if let CrateSupport::ConditionalNoStd { ref condition, .. } = self.support {
+            if !condition.eval(&self.active_cfg()) {
+                match self.features_to_disable() {
+                    Some(features) => lints.extend(features.iter().map(|_| Lint::StdFeature)),
+                    None => lints.push(Lint::UnsatisfiableNoStdCondition),
+                }
+            }
+        }
+        lints.extend(
+            self.leaked_offenses()
+                .into_iter()
+                .chain(self.active_offenses())
+                .map(|offense| offense.lint()),
+        );
+        lints
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.lint_levels[&lint]
     }
 
-    pub fn is_feature_active(&self, feature: &str) -> bool {
-        self.find_active_feature_by_name(feature).is_some()
+    /// Most severe level of the findings, or `Allow` if there are none. Crates that can not be
+    /// checked are only warned about, as they might support no_std.
+    pub fn max_level(&self) -> Level {
+        let unchecked_level = match self.support {
+            CrateSupport::Unchecked(_) => Level::Warn,
+            _ => Level::Allow,
+        };
+        self.lints()
+            .into_iter()
+            .map(|lint| self.level(lint))
+            .max()
+            .unwrap_or(Level::Allow)
+            .max(unchecked_level)
     }
 
     pub fn find_active_feature_by_name(&self, feature: &str) -> Option<&Feature> {
-        self.active_features.iter().find(|n| &n.name == feature)
+        self.active_features.iter().find(|n| n.name == feature)
     }
 }
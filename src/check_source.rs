@@ -1,14 +1,21 @@
+use std::collections::HashSet;
 use std::fmt;
 use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
-
-#[cfg(feature = "proc_macro_spans")]
 use std::io::BufRead;
-#[cfg(feature = "proc_macro_spans")]
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
 
+use crate::cfg::*;
 use crate::check::*;
+use crate::lint::*;
+use crate::module_tree::*;
+use crate::util::*;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum SourceOffense {
@@ -16,104 +23,221 @@ pub enum SourceOffense {
     /// Only valid for entry point file (main.rs / lib.rs).
     MissingNoStdAttribute,
     /// Source code contains an explicit `use std::` statement.
-    UseStdStatement(UseStdStmt),
+    UseStdStatement(StdUsage),
+    /// Source code contains a path rooted at `std` outside of a `use` statement, e.g. in a type
+    /// (`std::string::String`) or an expression (`::std::mem::swap(a, b)`).
+    StdPath(StdUsage),
+    /// Source code invokes a macro that is only available with `std`, like `println!`.
+    StdMacro(StdUsage),
 }
 
+/// Names from the std prelude that are only available with `alloc` in `no_std` crates.
+const ALLOC_PRELUDE_NAMES: &[&str] = &["Vec", "Box", "String"];
+
+/// Macros that are only available with `std`, together with a suggested replacement.
+const STD_MACROS: &[(&str, &str)] = &[
+    (
+        "print",
+        "`core::write!` on a `core::fmt::Write` implementation",
+    ),
+    (
+        "println",
+        "`core::writeln!` on a `core::fmt::Write` implementation",
+    ),
+    (
+        "eprint",
+        "`core::write!` on a `core::fmt::Write` implementation",
+    ),
+    (
+        "eprintln",
+        "`core::writeln!` on a `core::fmt::Write` implementation",
+    ),
+    (
+        "dbg",
+        "`core::writeln!` with `{:?}` on a `core::fmt::Write` implementation",
+    ),
+    (
+        "thread_local",
+        "a `static` with a type from `core::cell` or `core::sync::atomic`",
+    ),
+];
+
+/// Macros from the std prelude that require `alloc`, together with a suggested replacement.
+const ALLOC_MACROS: &[(&str, &str)] = &[("format", "`alloc::format!`"), ("vec", "`alloc::vec!`")];
+
+/// Result of checking the source of a crate.
 #[derive(Debug)]
-pub struct UseStdStmt {
+pub struct SourceCheck {
+    pub support: CrateSupport,
+    /// Usages of `alloc` (`extern crate alloc`, `alloc::` paths and prelude-less uses of `Vec`,
+    /// `Box`, `String` and `format!`), which don't hinder no_std support, but require a global
+    /// allocator.
+    pub alloc_usages: Vec<StdUsage>,
+}
+
+/// Location in the source code where an item from `std` (or `alloc`) is used.
+#[derive(Debug)]
+pub struct StdUsage {
     src_path: PathBuf,
-    item_tree: syn::UseTree,
+    span: Span,
+    /// Span of the first segment of the path, e.g. of `std` in `std::path::PathBuf`, which is
+    /// shared by all paths of a grouped use tree like `use std::{fmt, ops::Add};`.
+    root_span: Span,
+    /// `std::path::PathBuf` -> `["std", "path", "PathBuf"]`, `format!` -> `["format!"]`
+    path_parts: Vec<String>,
+    /// Path of the enclosing item, e.g. `crate::parser::Parser::new`.
+    item_path: String,
+    /// `#[cfg(...)]` predicates of the enclosing items, that all have to hold for the usage to be
+    /// compiled.
+    gates: Vec<CfgExpr>,
 }
 
-impl UseStdStmt {
-    #[cfg(feature = "proc_macro_spans")]
-    // TODO: can be made available without proc_macro_spans by parsing from UseTree
-    fn statement_str(&self) -> String {
-        let file = File::open(&self.src_path).unwrap();
-        let file = std::io::BufReader::new(file);
-        let line = file
-            .lines()
-            .skip(self.item_tree.span().start().line - 1)
-            .next()
-            .unwrap()
-            .unwrap();
+impl SourceOffense {
+    /// `#[cfg(...)]` predicates that have to hold for the offending code to be compiled.
+    pub fn gates(&self) -> &[CfgExpr] {
+        self.std_usage().map_or(&[], |usage| &usage.gates)
+    }
 
-        let raw_part: String = line
-            .chars()
-            .skip(self.item_tree.span().start().column)
-            .take(self.item_tree.span().end().column - self.item_tree.span().start().column)
-            .collect();
+    pub fn is_compiled_in(&self, cfg: &Cfg) -> bool {
+        self.std_usage()
+            .is_none_or(|usage| usage.is_compiled_in(cfg))
+    }
 
-        raw_part
+    /// Lint that reports the offense.
+    pub fn lint(&self) -> Lint {
+        match self {
+            SourceOffense::MissingNoStdAttribute => Lint::MissingNoStdAttribute,
+            SourceOffense::UseStdStatement(_) => Lint::UseStdStatement,
+            SourceOffense::StdPath(_) => Lint::StdPath,
+            SourceOffense::StdMacro(_) => Lint::StdMacro,
+        }
     }
 
-    #[cfg(feature = "proc_macro_spans")]
-    /// `std::path::PathBuf` -> `["std", "path", "PathBuf"]`
-    fn path_parts(&self) -> Vec<String> {
-        let raw_part = self.statement_str();
-        raw_part.split("::").map(|n| n.to_owned()).collect()
+    pub fn std_usage(&self) -> Option<&StdUsage> {
+        match self {
+            SourceOffense::MissingNoStdAttribute => None,
+            SourceOffense::UseStdStatement(usage) => Some(usage),
+            SourceOffense::StdPath(usage) => Some(usage),
+            SourceOffense::StdMacro(usage) => Some(usage),
+        }
     }
 }
 
-impl PartialEq for UseStdStmt {
-    fn eq(&self, other: &UseStdStmt) -> bool {
-        self.src_path == other.src_path
+impl StdUsage {
+    pub fn item_path(&self) -> &str {
+        &self.item_path
+    }
+
+    /// Path of the source file, relative to the current directory if it is inside of it.
+    pub fn relative_src_path(&self) -> &Path {
+        relative_to_current_dir(&self.src_path)
+    }
+
+    /// Line and (1-based) column of the usage.
+    pub fn line_column(&self) -> (usize, usize) {
+        (self.span.start().line, self.span.start().column + 1)
+    }
+
+    /// Line and (1-based) start and end columns of the first segment of the path.
+    pub fn root_location(&self) -> (usize, usize, usize) {
+        let (start, end) = (self.root_span.start(), self.root_span.end());
+        (start.line, start.column + 1, end.column + 1)
+    }
+
+    /// `#[cfg(...)]` predicates that have to hold for the usage to be compiled.
+    pub fn gates(&self) -> &[CfgExpr] {
+        &self.gates
+    }
+
+    /// `std::path::PathBuf`
+    pub fn path(&self) -> String {
+        self.path_parts.join("::")
+    }
+
+    pub fn is_compiled_in(&self, cfg: &Cfg) -> bool {
+        self.gates.iter().all(|gate| gate.eval(cfg))
+    }
+
+    /// Suggested replacement for a usage of a `std` or `alloc` macro, e.g. `alloc::format!` for
+    /// `format!`.
+    pub fn macro_replacement(&self) -> Option<&'static str> {
+        let name = match self.path_parts.as_slice() {
+            [name] => name.strip_suffix('!')?,
+            _ => return None,
+        };
+        STD_MACROS
+            .iter()
+            .chain(ALLOC_MACROS)
+            .find(|(macro_name, _)| *macro_name == name)
+            .map(|(_, replacement)| *replacement)
+    }
+
+    /// Suggested replacement for a `std::` path, e.g. `core::ops::Add` for `std::ops::Add`.
+    pub fn std_replacement(&self) -> Option<String> {
+        match self.path_parts[0].as_str() {
+            "std" => find_use_std_statement_replacement(&self.path_parts).map(|n| n.join("::")),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for StdUsage {
+    fn eq(&self, other: &StdUsage) -> bool {
+        self.src_path == other.src_path && self.path_parts == other.path_parts
     }
 }
-impl Eq for UseStdStmt {}
+impl Eq for StdUsage {}
 
-#[cfg(feature = "proc_macro_spans")]
-impl fmt::Display for UseStdStmt {
+impl fmt::Display for StdUsage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let file = File::open(&self.src_path).unwrap();
         let file = std::io::BufReader::new(file);
         let line = file
             .lines()
-            .skip(self.item_tree.span().start().line - 1)
-            .next()
+            .nth(self.span.start().line - 1)
             .unwrap()
             .unwrap();
 
-        let statement_str = self.statement_str();
-
-        let replacement_suggestion = find_use_std_statement_replacement(&self.path_parts());
-        let replacement_suggestion = replacement_suggestion.map(|n| n.join("::"));
+        let replacement_suggestion = match self.path_parts[0].as_str() {
+            "std" => self.std_replacement().map(|n| format!("`{}`", n)),
+            _ => self.macro_replacement().map(|n| n.to_owned()),
+        };
 
+        let (line_num, column) = self.line_column();
         writeln!(
             f,
             "   --> {src}:{line}:{column}",
-            src = self
-                .src_path
-                .strip_prefix(std::env::current_dir().unwrap())
-                .unwrap()
-                .display(),
-            line = self.item_tree.span().start().line,
-            column = self.item_tree.span().start().column
+            src = self.relative_src_path().display(),
+            line = line_num,
+            column = column
         )?;
         writeln!(f, "    |")?;
 
         writeln!(
             f,
             "{line_num:<4}|{line}",
-            line_num = self.item_tree.span().start().line,
+            line_num = self.span.start().line,
             line = line
         )?;
 
-        let mut underline: Vec<char> = vec![];
-        for _ in 0..self.item_tree.span().start().column {
-            underline.push(' ');
-        }
-        for _ in self.item_tree.span().start().column..self.item_tree.span().end().column {
-            underline.push('^');
-        }
-        let underline: String = underline.into_iter().collect();
+        // paths with generic arguments can span several lines, of which only the first one is
+        // printed
+        let end_column = match self.span.end().line == self.span.start().line {
+            true => self.span.end().column,
+            false => line.chars().count(),
+        };
+        let underline = format!(
+            "{}{}",
+            " ".repeat(self.span.start().column),
+            "^".repeat(end_column.saturating_sub(self.span.start().column))
+        );
 
         writeln!(f, "    |{line}", line = underline)?;
         if let Some(replacement_suggestion) = replacement_suggestion {
             writeln!(
                 f,
-                "help: Try replacing `{original}` with `{replacement}`.",
-                original = statement_str,
+                "help: Try replacing `{original}` with {replacement}.",
+                original = self.path(),
                 replacement = replacement_suggestion
             )?;
         }
@@ -121,137 +245,652 @@ impl fmt::Display for UseStdStmt {
     }
 }
 
-#[cfg(not(feature = "proc_macro_spans"))]
-impl fmt::Display for UseStdStmt {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(
-            f,
-            "   --> {src}",
-            src = self
-                .src_path
-                .strip_prefix(std::env::current_dir().unwrap())
-                .unwrap_or(&self.src_path)
-                .display(),
-        )
-    }
+/// Collects all usages of `std` in a file, including the ones nested in functions, impls, traits
+/// and inline modules.
+struct StdUsageVisitor<'a> {
+    src_path: &'a Path,
+    /// Path of the item that is currently visited, e.g. `["crate", "parser", "Parser", "new"]`.
+    item_path: Vec<String>,
+    /// `#[cfg(...)]` predicates of the item, statement or expression that is currently visited and
+    /// its parents.
+    gates: Vec<CfgExpr>,
+    /// Whether `#[test]` functions and `#[cfg(test)]` items are checked.
+    include_tests: bool,
+    /// Names in the file that shadow the `alloc` names of the std prelude, e.g. via
+    /// `use heapless::Vec;`.
+    shadowed_prelude_names: HashSet<String>,
+    /// Macros of `STD_MACROS` and `ALLOC_MACROS` that the crate defines or imports itself.
+    shadowed_macro_names: &'a HashSet<String>,
+    offenses: Vec<SourceOffense>,
+    alloc_usages: Vec<StdUsage>,
 }
 
-pub fn get_crate_support_from_source(main_src_path: &PathBuf) -> CrateSupport {
-    let main_file_support = check_source(main_src_path, true);
+impl<'a> StdUsageVisitor<'a> {
+    fn new(
+        module_file: &'a ModuleFile,
+        shadowed_macro_names: &'a HashSet<String>,
+        include_tests: bool,
+    ) -> Self {
+        let mut gates = module_file.gates.clone();
+        // `#![cfg(...)]` at the top of the file
+        gates.extend(CfgExpr::from_cfg_attributes(&module_file.syntax.attrs));
+        Self {
+            src_path: &module_file.src_path,
+            item_path: module_file.module_path.clone(),
+            gates,
+            include_tests,
+            shadowed_prelude_names: shadowed_prelude_names(&module_file.syntax.items),
+            shadowed_macro_names,
+            offenses: vec![],
+            alloc_usages: vec![],
+        }
+    }
 
-    let mut offenses = vec![];
-    match main_file_support {
-        CrateSupport::OnlyWithoutFeature(_) => return main_file_support,
-        CrateSupport::ProcMacro => return main_file_support,
-        CrateSupport::SourceOffenses(mut off) => offenses.append(&mut off),
-        CrateSupport::NoOffenseDetected => {}
-    };
+    fn usage(&self, span: Span, root_span: Span, path_parts: Vec<String>) -> StdUsage {
+        StdUsage {
+            src_path: self.src_path.to_owned(),
+            span,
+            root_span,
+            path_parts,
+            item_path: self.item_path.join("::"),
+            gates: self.gates.clone(),
+        }
+    }
 
-    let other_source_files_pattern = format!(
-        "{}/**/*.rs",
-        main_src_path.parent().unwrap().to_str().unwrap(),
-    );
-    let other_source_files = glob::glob(&other_source_files_pattern).unwrap();
+    fn push_alloc_usage(&mut self, span: Span, root_span: Span, path_parts: Vec<String>) {
+        let usage = self.usage(span, root_span, path_parts);
+        // `Vec<Vec<u8>>` only needs to be reported once
+        let is_duplicate = self
+            .alloc_usages
+            .iter()
+            .any(|n| n == &usage && n.item_path == usage.item_path && n.gates == usage.gates);
+        if !is_duplicate {
+            self.alloc_usages.push(usage);
+        }
+    }
 
-    for other_source_file in other_source_files {
-        let file_support = check_source(&other_source_file.unwrap(), false);
-        match file_support {
-            CrateSupport::SourceOffenses(mut off) => offenses.append(&mut off),
-            _ => {}
+    fn visit_gated<F: FnOnce(&mut Self)>(&mut self, attrs: &[syn::Attribute], visit_inner: F) {
+        let gates = CfgExpr::from_cfg_attributes(attrs);
+        if !self.include_tests && (is_test_fn(attrs) || gates.iter().any(|n| n.requires_test())) {
+            return;
         }
+        let gates_len = self.gates.len();
+        self.gates.extend(gates);
+        visit_inner(self);
+        self.gates.truncate(gates_len);
     }
 
-    match offenses.is_empty() {
-        true => CrateSupport::NoOffenseDetected,
-        false => CrateSupport::SourceOffenses(offenses),
+    fn visit_scope<F: FnOnce(&mut Self)>(&mut self, ident: &syn::Ident, visit_inner: F) {
+        self.item_path.push(ident.to_string());
+        visit_inner(self);
+        self.item_path.pop();
     }
 }
 
-fn check_source(source_path: &PathBuf, is_main_file: bool) -> CrateSupport {
-    let mut file = File::open(&source_path).expect("Unable to open file");
+impl<'ast, 'a> Visit<'ast> for StdUsageVisitor<'a> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        self.visit_gated(item_attrs(item), |v| visit::visit_item(v, item));
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast syn::ImplItem) {
+        let attrs: &[syn::Attribute] = match item {
+            syn::ImplItem::Const(item) => &item.attrs,
+            syn::ImplItem::Method(item) => &item.attrs,
+            syn::ImplItem::Type(item) => &item.attrs,
+            syn::ImplItem::Macro(item) => &item.attrs,
+            _ => &[],
+        };
+        self.visit_gated(attrs, |v| visit::visit_impl_item(v, item));
+    }
 
-    let mut src = String::new();
-    file.read_to_string(&mut src).expect("Unable to read file");
+    fn visit_trait_item(&mut self, item: &'ast syn::TraitItem) {
+        let attrs: &[syn::Attribute] = match item {
+            syn::TraitItem::Const(item) => &item.attrs,
+            syn::TraitItem::Method(item) => &item.attrs,
+            syn::TraitItem::Type(item) => &item.attrs,
+            syn::TraitItem::Macro(item) => &item.attrs,
+            _ => &[],
+        };
+        self.visit_gated(attrs, |v| visit::visit_trait_item(v, item));
+    }
 
-    let syntax = syn::parse_file(&src).expect("Unable to parse file");
+    fn visit_field(&mut self, field: &'ast syn::Field) {
+        self.visit_gated(&field.attrs, |v| visit::visit_field(v, field));
+    }
 
-    for attr in &syntax.attrs {
-        if let Some(conditional_attr) = ConditionalAttribute::from_attribute(&attr) {
-            let no_std_ident: syn::Ident = syn::parse_quote!(no_std);
-            if conditional_attr.attribute == no_std_ident {
-                if let Some(required_feature) = conditional_attr.required_feature() {
-                    let mut feature_name = required_feature.to_string();
-                    feature_name = feature_name[1..feature_name.len() - 1].to_owned();
-                    return CrateSupport::OnlyWithoutFeature(feature_name);
+    fn visit_variant(&mut self, variant: &'ast syn::Variant) {
+        self.visit_gated(&variant.attrs, |v| visit::visit_variant(v, variant));
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        self.visit_gated(&local.attrs, |v| visit::visit_local(v, local));
+    }
+
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        self.visit_gated(expr_attrs(expr), |v| visit::visit_expr(v, expr));
+    }
+
+    fn visit_arm(&mut self, arm: &'ast syn::Arm) {
+        self.visit_gated(&arm.attrs, |v| visit::visit_arm(v, arm));
+    }
+
+    fn visit_field_value(&mut self, field: &'ast syn::FieldValue) {
+        self.visit_gated(&field.attrs, |v| visit::visit_field_value(v, field));
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        // a leading `::` (`use ::std::io;`) doesn't change the flattened path
+        for (path_parts, span, root_span) in flatten_use_tree(&item.tree) {
+            match path_parts[0].as_str() {
+                "std" => {
+                    let stmt = self.usage(span, root_span, path_parts);
+                    self.offenses.push(SourceOffense::UseStdStatement(stmt));
                 }
+                "alloc" => self.push_alloc_usage(span, root_span, path_parts),
+                _ => {}
             }
         }
     }
 
-    let mut offenses = vec![];
+    fn visit_item_extern_crate(&mut self, item: &'ast syn::ItemExternCrate) {
+        if item.ident == "alloc" {
+            self.push_alloc_usage(item.span(), item.ident.span(), vec!["alloc".to_owned()]);
+        }
+    }
 
-    let use_statements: Vec<_> = syntax
-        .items
-        .iter()
-        .filter_map(|item| match item {
-            syn::Item::Use(item) => Some(item),
-            _ => None,
-        })
-        .collect();
-
-    let std_ident: syn::Ident = syn::parse_quote!(std);
-    for use_statement in &use_statements {
-        match use_statement.tree {
-            syn::UseTree::Path(ref first_path) => {
-                let first_ident = &first_path.ident;
-                if first_ident == &std_ident {
-                    let stmt = UseStdStmt {
-                        src_path: source_path.clone(),
-                        item_tree: use_statement.tree.clone(),
-                    };
-                    offenses.push(SourceOffense::UseStdStatement(stmt));
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        // std paths in the body of a `macro_rules!` definition only matter where it is invoked
+        if mac.path.is_ident("macro_rules") {
+            return;
+        }
+
+        if let Some(name) = mac.path.get_ident().map(|n| n.to_string()) {
+            let is_shadowed = self.shadowed_macro_names.contains(&name);
+            let is_macro = |macros: &[(&str, &str)]| macros.iter().any(|(n, _)| *n == name);
+            if !is_shadowed && is_macro(STD_MACROS) {
+                let span = mac.path.span();
+                let usage = self.usage(span, span, vec![format!("{}!", name)]);
+                self.offenses.push(SourceOffense::StdMacro(usage));
+            } else if !is_shadowed && is_macro(ALLOC_MACROS) {
+                let span = mac.path.span();
+                self.push_alloc_usage(span, span, vec![format!("{}!", name)]);
+            }
+        }
+        visit::visit_macro(self, mac);
+
+        // most macros (`println!`, `assert_eq!`, `vec!`, ...) take a list of expressions, which
+        // can be checked like regular code
+        let parser = Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated;
+        match syn::parse::Parser::parse2(parser, mac.tokens.clone()) {
+            Ok(exprs) => {
+                for expr in &exprs {
+                    self.visit_expr(expr);
                 }
             }
-            _ => {
-                // FIXME: #19 - ignore non-trivial use statements for now
+            Err(_) => {
+                for (path_parts, span) in std_paths_in_tokens(mac.tokens.clone()) {
+                    // only the `std` segment is highlighted
+                    let usage = self.usage(span, span, path_parts);
+                    self.offenses.push(SourceOffense::StdPath(usage));
+                }
             }
         }
     }
 
-    if is_main_file {
-        let always_no_std: syn::Attribute = syn::parse_quote!(#![no_std]);
-        let contains_always_no_std = syntax.attrs.contains(&always_no_std);
-        if !contains_always_no_std {
-            let not_test_no_std: syn::Attribute = syn::parse_quote!(#![cfg_attr(not(test), no_std)]);
-            let contains_not_test_no_std = syntax.attrs.contains(&not_test_no_std);
-            if !contains_not_test_no_std {
-                offenses.push(SourceOffense::MissingNoStdAttribute);
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        // a single `std` segment is most likely a local binding, not the crate
+        let is_std_path = path.segments.len() > 1 && path.segments[0].ident == "std";
+        if is_std_path {
+            let path_parts = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            let usage = self.usage(path.span(), path.segments[0].ident.span(), path_parts);
+            self.offenses.push(SourceOffense::StdPath(usage));
+        }
+
+        let first_segment = path.segments[0].ident.to_string();
+        let is_alloc_path = path.segments.len() > 1 && first_segment == "alloc";
+        // `Vec::new()` or `Box<T>` without an import, i.e. from the std prelude or a glob import
+        let is_alloc_prelude_path = path.leading_colon.is_none()
+            && ALLOC_PRELUDE_NAMES.contains(&first_segment.as_str())
+            && !self.shadowed_prelude_names.contains(&first_segment);
+        if is_alloc_path || is_alloc_prelude_path {
+            let path_parts = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            self.push_alloc_usage(path.span(), path.segments[0].ident.span(), path_parts);
+        }
+        // generic arguments may contain further paths, e.g. `Option<std::string::String>`
+        visit::visit_path(self, path);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        self.visit_scope(&item.ident, |v| visit::visit_item_mod(v, item));
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.visit_scope(&item.sig.ident, |v| visit::visit_item_fn(v, item));
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        self.visit_scope(&item.ident, |v| visit::visit_item_struct(v, item));
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        self.visit_scope(&item.ident, |v| visit::visit_item_enum(v, item));
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.visit_scope(&item.ident, |v| visit::visit_item_trait(v, item));
+    }
+
+    fn visit_trait_item_method(&mut self, item: &'ast syn::TraitItemMethod) {
+        self.visit_scope(&item.sig.ident, |v| visit::visit_trait_item_method(v, item));
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        // `impl<T> Parser<T>` and `impl Display for Parser` are both scoped as `Parser`
+        match *item.self_ty {
+            syn::Type::Path(ref type_path) if type_path.qself.is_none() => {
+                let self_ident = &type_path.path.segments.last().unwrap().ident;
+                self.visit_scope(self_ident, |v| visit::visit_item_impl(v, item));
+            }
+            _ => visit::visit_item_impl(self, item),
+        }
+    }
+
+    fn visit_impl_item_method(&mut self, item: &'ast syn::ImplItemMethod) {
+        self.visit_scope(&item.sig.ident, |v| visit::visit_impl_item_method(v, item));
+    }
+}
+
+/// Paths rooted at `std` in the tokens of a macro invocation that can not be parsed as
+/// expressions, e.g. `thread_local! { static CACHE: std::cell::RefCell<u8> = ... }`.
+fn std_paths_in_tokens(tokens: TokenStream) -> Vec<(Vec<String>, Span)> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut paths = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            TokenTree::Group(ref group) => paths.extend(std_paths_in_tokens(group.stream())),
+            TokenTree::Ident(ref ident) if ident == "std" => {
+                let mut path_parts = vec![ident.to_string()];
+                // `std` `:` `:` `ident` ...
+                while let (Some(TokenTree::Punct(first)), Some(TokenTree::Punct(second))) =
+                    (tokens.get(i + 1), tokens.get(i + 2))
+                {
+                    if first.as_char() != ':' || second.as_char() != ':' {
+                        break;
+                    }
+                    match tokens.get(i + 3) {
+                        Some(TokenTree::Ident(segment)) => path_parts.push(segment.to_string()),
+                        _ => break,
+                    }
+                    i += 3;
+                }
+                if path_parts.len() > 1 {
+                    paths.push((path_parts, ident.span()));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    paths
+}
+
+/// Macros of `STD_MACROS` and `ALLOC_MACROS` that are defined via `macro_rules!` or imported
+/// somewhere in the crate, e.g. a custom `println!` that writes to a serial port.
+fn shadowed_macro_names(module_files: &[ModuleFile]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for module_file in module_files {
+        collect_macro_names(&module_file.syntax.items, &mut names);
+    }
+    names.retain(|name| {
+        STD_MACROS
+            .iter()
+            .chain(ALLOC_MACROS)
+            .any(|(macro_name, _)| macro_name == name)
+    });
+    names
+}
+
+fn collect_macro_names(items: &[syn::Item], names: &mut HashSet<String>) {
+    for item in items {
+        match item {
+            syn::Item::Macro(item) if item.mac.path.is_ident("macro_rules") => {
+                if let Some(ref ident) = item.ident {
+                    names.insert(ident.to_string());
+                }
+            }
+            syn::Item::Use(item) => collect_use_tree_bindings(&item.tree, names),
+            syn::Item::Mod(item) => {
+                if let Some((_, ref items)) = item.content {
+                    collect_macro_names(items, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `#[test]`, but also `#[tokio::test]` and similar.
+fn is_test_fn(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "test")
+    })
+}
+
+/// Names of `ALLOC_PRELUDE_NAMES` that are imported or defined somewhere in `items`.
+///
+/// Imports from `alloc` also count, as the import itself is already reported.
+fn shadowed_prelude_names(items: &[syn::Item]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for item in items {
+        match item {
+            syn::Item::Use(item) => collect_use_tree_bindings(&item.tree, &mut names),
+            syn::Item::Struct(item) => {
+                names.insert(item.ident.to_string());
+            }
+            syn::Item::Enum(item) => {
+                names.insert(item.ident.to_string());
+            }
+            syn::Item::Type(item) => {
+                names.insert(item.ident.to_string());
+            }
+            syn::Item::Union(item) => {
+                names.insert(item.ident.to_string());
+            }
+            syn::Item::Mod(item) => {
+                if let Some((_, ref items)) = item.content {
+                    names.extend(shadowed_prelude_names(items));
+                }
+            }
+            _ => {}
+        }
+    }
+    names.retain(|name| ALLOC_PRELUDE_NAMES.contains(&name.as_str()));
+    names
+}
+
+/// Names that a use tree brings into scope, e.g. `Vec` for `use heapless::Vec;`.
+fn collect_use_tree_bindings(tree: &syn::UseTree, names: &mut HashSet<String>) {
+    match tree {
+        syn::UseTree::Path(path) => collect_use_tree_bindings(&path.tree, names),
+        syn::UseTree::Name(name) => {
+            names.insert(name.ident.to_string());
+        }
+        syn::UseTree::Rename(rename) => {
+            names.insert(rename.rename.to_string());
+        }
+        syn::UseTree::Glob(_) => {}
+        syn::UseTree::Group(group) => {
+            for item in &group.items {
+                collect_use_tree_bindings(item, names);
+            }
+        }
+    }
+}
+
+fn item_attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(item) => &item.attrs,
+        syn::Item::Enum(item) => &item.attrs,
+        syn::Item::ExternCrate(item) => &item.attrs,
+        syn::Item::Fn(item) => &item.attrs,
+        syn::Item::ForeignMod(item) => &item.attrs,
+        syn::Item::Impl(item) => &item.attrs,
+        syn::Item::Macro(item) => &item.attrs,
+        syn::Item::Macro2(item) => &item.attrs,
+        syn::Item::Mod(item) => &item.attrs,
+        syn::Item::Static(item) => &item.attrs,
+        syn::Item::Struct(item) => &item.attrs,
+        syn::Item::Trait(item) => &item.attrs,
+        syn::Item::TraitAlias(item) => &item.attrs,
+        syn::Item::Type(item) => &item.attrs,
+        syn::Item::Union(item) => &item.attrs,
+        syn::Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+fn expr_attrs(expr: &syn::Expr) -> &[syn::Attribute] {
+    match expr {
+        syn::Expr::Array(expr) => &expr.attrs,
+        syn::Expr::Assign(expr) => &expr.attrs,
+        syn::Expr::AssignOp(expr) => &expr.attrs,
+        syn::Expr::Async(expr) => &expr.attrs,
+        syn::Expr::Await(expr) => &expr.attrs,
+        syn::Expr::Binary(expr) => &expr.attrs,
+        syn::Expr::Block(expr) => &expr.attrs,
+        syn::Expr::Box(expr) => &expr.attrs,
+        syn::Expr::Break(expr) => &expr.attrs,
+        syn::Expr::Call(expr) => &expr.attrs,
+        syn::Expr::Cast(expr) => &expr.attrs,
+        syn::Expr::Closure(expr) => &expr.attrs,
+        syn::Expr::Continue(expr) => &expr.attrs,
+        syn::Expr::Field(expr) => &expr.attrs,
+        syn::Expr::ForLoop(expr) => &expr.attrs,
+        syn::Expr::Group(expr) => &expr.attrs,
+        syn::Expr::If(expr) => &expr.attrs,
+        syn::Expr::Index(expr) => &expr.attrs,
+        syn::Expr::Let(expr) => &expr.attrs,
+        syn::Expr::Lit(expr) => &expr.attrs,
+        syn::Expr::Loop(expr) => &expr.attrs,
+        syn::Expr::Macro(expr) => &expr.attrs,
+        syn::Expr::Match(expr) => &expr.attrs,
+        syn::Expr::MethodCall(expr) => &expr.attrs,
+        syn::Expr::Paren(expr) => &expr.attrs,
+        syn::Expr::Path(expr) => &expr.attrs,
+        syn::Expr::Range(expr) => &expr.attrs,
+        syn::Expr::Reference(expr) => &expr.attrs,
+        syn::Expr::Repeat(expr) => &expr.attrs,
+        syn::Expr::Return(expr) => &expr.attrs,
+        syn::Expr::Struct(expr) => &expr.attrs,
+        syn::Expr::Try(expr) => &expr.attrs,
+        syn::Expr::TryBlock(expr) => &expr.attrs,
+        syn::Expr::Tuple(expr) => &expr.attrs,
+        syn::Expr::Type(expr) => &expr.attrs,
+        syn::Expr::Unary(expr) => &expr.attrs,
+        syn::Expr::Unsafe(expr) => &expr.attrs,
+        syn::Expr::While(expr) => &expr.attrs,
+        syn::Expr::Yield(expr) => &expr.attrs,
+        _ => &[],
+    }
+}
+
+/// Flattens a use tree into the paths it imports, together with the span that should be
+/// highlighted for each of them and the span of their first segment.
+///
+/// `use {std::fs, core::fmt::{self, Debug}};` -> `std::fs`, `core::fmt::self`, `core::fmt::Debug`
+fn flatten_use_tree(tree: &syn::UseTree) -> Vec<(Vec<String>, Span, Span)> {
+    let mut leaves = vec![];
+    flatten_use_tree_into(tree, &[], None, None, &mut leaves);
+    leaves
+}
+
+/// `highlight_span` is the span of the outermost tree that only leads to a single leaf, so that
+/// `std::fs` is highlighted as a whole, while in `std::{fs, io}` only `fs` and `io` are.
+fn flatten_use_tree_into(
+    tree: &syn::UseTree,
+    prefix: &[String],
+    highlight_span: Option<Span>,
+    root_span: Option<Span>,
+    leaves: &mut Vec<(Vec<String>, Span, Span)>,
+) {
+    let highlight_span = highlight_span.unwrap_or_else(|| tree.span());
+    let root_span = root_span.unwrap_or_else(|| match tree {
+        syn::UseTree::Path(path) => path.ident.span(),
+        syn::UseTree::Name(name) => name.ident.span(),
+        syn::UseTree::Rename(rename) => rename.ident.span(),
+        _ => tree.span(),
+    });
+    let with_part = |part: String| {
+        let mut path_parts = prefix.to_vec();
+        path_parts.push(part);
+        path_parts
+    };
+    match tree {
+        syn::UseTree::Path(path) => flatten_use_tree_into(
+            &path.tree,
+            &with_part(path.ident.to_string()),
+            Some(highlight_span),
+            Some(root_span),
+            leaves,
+        ),
+        syn::UseTree::Name(name) => {
+            leaves.push((with_part(name.ident.to_string()), highlight_span, root_span))
+        }
+        syn::UseTree::Rename(rename) => leaves.push((
+            with_part(rename.ident.to_string()),
+            highlight_span,
+            root_span,
+        )),
+        syn::UseTree::Glob(_) => {
+            leaves.push((with_part("*".to_owned()), highlight_span, root_span))
+        }
+        syn::UseTree::Group(group) => {
+            // `{std::fs, core::fmt}` has no shared first segment
+            let root_span = Some(root_span).filter(|_| !prefix.is_empty());
+            for item in &group.items {
+                flatten_use_tree_into(item, prefix, None, root_span, leaves);
+            }
+        }
+    }
+}
+
+/// `no_std` attribute of a crate root.
+enum NoStdAttribute {
+    /// `#![no_std]` or a conditional `no_std` attribute that holds independently of features,
+    /// like `#![cfg_attr(not(test), no_std)]`.
+    Always,
+    /// `#![cfg_attr(<condition>, no_std)]` with a condition that depends on features, or that
+    /// doesn't hold for the target.
+    Conditional(CfgExpr),
+    Missing,
+}
+
+/// Checks the crate with the provided root file. Conditional `no_std` attributes that don't
+/// depend on features are evaluated for `target_cfg`.
+///
+/// Test-only code (`#[test]` functions and `#[cfg(test)]` items) is skipped, unless
+/// `include_tests` is set.
+pub fn get_crate_support_from_source(
+    main_src_path: &Path,
+    target_cfg: &Cfg,
+    include_tests: bool,
+) -> Result<SourceCheck, SourceError> {
+    let module_files = crate_module_files(main_src_path)?;
+    let shadowed_macro_names = shadowed_macro_names(&module_files);
+
+    let mut no_std_condition = None;
+    let mut offenses = vec![];
+    let mut alloc_usages = vec![];
+    for module_file in &module_files {
+        // e.g. `#[cfg(test)] mod tests;`
+        if !include_tests && module_file.gates.iter().any(|n| n.requires_test()) {
+            continue;
+        }
+
+        if module_file.is_root {
+            match find_no_std_attribute(&module_file.syntax, target_cfg) {
+                NoStdAttribute::Always => {}
+                NoStdAttribute::Conditional(condition) => no_std_condition = Some(condition),
+                NoStdAttribute::Missing => offenses.push(SourceOffense::MissingNoStdAttribute),
             }
         }
+
+        let mut visitor = StdUsageVisitor::new(module_file, &shadowed_macro_names, include_tests);
+        visitor.visit_file(&module_file.syntax);
+        offenses.append(&mut visitor.offenses);
+        alloc_usages.append(&mut visitor.alloc_usages);
+    }
+
+    let support = match no_std_condition {
+        // usages of std are only a problem if they are not gated by the features that have to
+        // be deactivated for no_std, which is decided by the caller
+        Some(condition) => CrateSupport::ConditionalNoStd {
+            condition,
+            offenses,
+        },
+        None if offenses.is_empty() => CrateSupport::NoOffenseDetected,
+        None => CrateSupport::SourceOffenses(offenses),
+    };
+    Ok(SourceCheck {
+        support,
+        alloc_usages,
+    })
+}
+
+fn find_no_std_attribute(syntax: &syn::File, target_cfg: &Cfg) -> NoStdAttribute {
+    let always_no_std: syn::Attribute = syn::parse_quote!(#![no_std]);
+    if syntax.attrs.contains(&always_no_std) {
+        return NoStdAttribute::Always;
     }
 
-    match offenses.is_empty() {
-        true => CrateSupport::NoOffenseDetected,
-        false => CrateSupport::SourceOffenses(offenses),
+    let mut conditions = vec![];
+    for attr in &syntax.attrs {
+        if let Some(conditional_attr) = ConditionalAttribute::from_attribute(attr) {
+            if !conditional_attr.contains_attribute("no_std") {
+                continue;
+            }
+            let condition = conditional_attr.condition;
+            if condition.features().is_empty() && condition.eval(target_cfg) {
+                return NoStdAttribute::Always;
+            }
+            conditions.push(condition);
+        }
+    }
+    // the crate is `no_std` if any of the conditions holds
+    match conditions.len() {
+        0 => NoStdAttribute::Missing,
+        1 => NoStdAttribute::Conditional(conditions.pop().unwrap()),
+        _ => NoStdAttribute::Conditional(CfgExpr::Any(conditions)),
     }
 }
 
+/// Directory of the HTML docs of the toolchain that `rustup` selects for the current directory,
+/// or `None` if rustup or the `rust-docs` component are not installed. Only looked up once, as it
+/// is needed for every usage of `std`.
+fn rust_doc_dir() -> Option<&'static Path> {
+    static DOC_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DOC_DIR
+        .get_or_init(|| {
+            let rustup_output = Command::new("rustup")
+                .args(["which", "rustdoc"])
+                .output()
+                .ok()?;
+            if !rustup_output.status.success() {
+                return None;
+            }
+            let rustdoc_path = String::from_utf8(rustup_output.stdout).ok()?;
+            let doc_dir = Path::new(rustdoc_path.trim())
+                .parent()?
+                .join("../share/doc/rust/html");
+            doc_dir.join("core").is_dir().then_some(doc_dir)
+        })
+        .as_deref()
+}
+
 /// Really hacky way of trying to find a replacment for a `use std::` statment.
 ///
 /// Right now checks if `std` can be replaced by `core` in the following way:
 /// - Find out directory of `rustdoc` via `rustup which rustdoc`
 /// - Infer rust doc directory for that
 /// - Try to find a file in `core` docs that would serve as replacment for `std` item
-#[allow(dead_code)]
 fn find_use_std_statement_replacement(path_parts: &[String]) -> Option<Vec<String>> {
-    let rustup_output = std::process::Command::new("rustup")
-        .args(&vec!["which", "rustdoc"])
-        .output()
-        .expect("failed to execute rustup");
-    let rustdoc_dir = String::from_utf8(rustup_output.stdout).unwrap();
-    let mut doc_dir = PathBuf::from(&rustdoc_dir);
-    doc_dir.pop();
-    doc_dir = doc_dir.join("../share/doc/rust/html");
+    // `use std as s;` can not be replaced item by item
+    if path_parts.len() < 2 {
+        return None;
+    }
+    let doc_dir = rust_doc_dir()?;
 
     let mut core_dir = doc_dir.join("core");
     for path_part in path_parts.iter().skip(1).take(path_parts.len() - 2) {
@@ -260,36 +899,33 @@ fn find_use_std_statement_replacement(path_parts: &[String]) -> Option<Vec<Strin
 
     let glob_pattern = format!(
         "{}/*.{}.html",
-        core_dir.to_str().unwrap(),
+        core_dir.to_str()?,
         path_parts.last().unwrap()
     );
-    let mut glob_files = glob::glob(&glob_pattern).unwrap();
-    match glob_files.next().is_some() {
-        true => {
-            let replacement_path = vec!["core".to_owned()]
-                .into_iter()
-                .chain(path_parts.into_iter().skip(1).map(|n| n.clone()))
-                .collect();
-            return Some(replacement_path);
-        }
-        false => {}
+    let mut glob_files = glob::glob(&glob_pattern).ok()?;
+    if glob_files.next().is_some() {
+        let replacement_path = vec!["core".to_owned()]
+            .into_iter()
+            .chain(path_parts.iter().skip(1).cloned())
+            .collect();
+        return Some(replacement_path);
     };
 
     // check for module index files, so that module use statments like `use std::ops`
     // are checked correctly
     let glob_pattern_index = format!(
         "{}/{}/index.html",
-        core_dir.to_str().unwrap(),
+        core_dir.to_str()?,
         path_parts.last().unwrap()
     );
-    let mut glob_files = glob::glob(&glob_pattern_index).unwrap();
+    let mut glob_files = glob::glob(&glob_pattern_index).ok()?;
     match glob_files.next().is_some() {
         true => {
             let replacement_path = vec!["core".to_owned()]
                 .into_iter()
-                .chain(path_parts.into_iter().skip(1).map(|n| n.clone()))
+                .chain(path_parts.iter().skip(1).cloned())
                 .collect();
-            return Some(replacement_path);
+            Some(replacement_path)
         }
         false => None,
     }
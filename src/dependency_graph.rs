@@ -0,0 +1,405 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use cargo_metadata::{DependencyKind, Package};
+
+use crate::cfg::*;
+use crate::ext::*;
+use crate::metadata::*;
+
+/// All packages that are reachable from a root package via normal dependencies, together with the
+/// features that are activated for each of them.
+///
+/// The packages and their features are taken from the resolve graph of `cargo metadata`, which
+/// has to be run with the features and platform that are checked. The features that the
+/// dependents request for each package are modeled separately, to explain why a feature is active.
+pub struct DependencyGraph<'a> {
+    metadata: &'a CargoMetadata,
+    resolver: Resolver,
+    /// Package ids of the packages that are linked into the root, in the order they were
+    /// discovered, starting with the root.
+    package_ids: Vec<String>,
+    /// Package id of the package through which a package was discovered first.
+    parents: HashMap<String, String>,
+    /// Features that are requested for a package by the root (via CLI flags) or its dependents.
+    features: HashMap<String, Vec<Feature>>,
+    /// Active features of a package that are only activated because resolver 1 unifies them with
+    /// the ones requested via build dependencies, dev dependencies and proc macros.
+    resolver1_only_features: HashMap<String, Vec<Feature>>,
+    workspace_features: &'a WorkspaceFeatures,
+}
+
+/// Features that the workspace members request for each package.
+///
+/// `cargo metadata` resolves the features of all workspace members at once, so this is used to
+/// tell the features that are unified from other members apart from the ones that the model can
+/// not explain.
+pub struct WorkspaceFeatures {
+    /// package id -> names of the active features
+    features: HashMap<String, HashSet<String>>,
+}
+
+/// Difference between the features that the model derives for a package and the ones resolved by
+/// cargo.
+pub struct FeatureModelDifference<'a> {
+    pub package: &'a Package,
+    /// Features that cargo activates, but the model doesn't.
+    pub unexplained_features: Vec<String>,
+    /// Features that the model activates, but cargo doesn't.
+    pub unresolved_features: Vec<String>,
+}
+
+impl WorkspaceFeatures {
+    /// Models the features of all packages for the provided workspace members, each of which is
+    /// built with its own features.
+    ///
+    /// Like in the resolve of `cargo metadata`, the features of all dependency kinds are unified.
+    pub fn model(
+        metadata: &CargoMetadata,
+        members: Vec<(&Package, Vec<Feature>)>,
+        target_cfg: &Cfg,
+    ) -> Self {
+        let mut features: HashMap<String, HashSet<String>> = HashMap::new();
+        for (member, member_features) in members {
+            let requested =
+                requested_features(metadata, member, member_features, target_cfg, Resolver::V1);
+            for (package_id, package_features) in requested {
+                let package = metadata.find_package(&package_id).unwrap();
+                features.entry(package_id).or_default().extend(
+                    package
+                        .active_features_for_features(&package_features)
+                        .into_iter()
+                        .map(|n| n.name),
+                );
+            }
+        }
+        WorkspaceFeatures { features }
+    }
+
+    /// Whether any workspace member activates the feature of the package.
+    pub fn contains(&self, package_id: &str, feature_name: &str) -> bool {
+        self.features
+            .get(package_id)
+            .is_some_and(|features| features.contains(feature_name))
+    }
+}
+
+impl<'a> DependencyGraph<'a> {
+    /// Walks the resolve graph from `root`, which is built with `root_features`.
+    ///
+    /// Dependencies of proc macros are not checked, as they are never linked into the final
+    /// artifact, and neither are dependencies that are declared for other platforms than
+    /// `target_cfg`.
+    pub fn walk(
+        metadata: &'a CargoMetadata,
+        root: &Package,
+        root_features: Vec<Feature>,
+        workspace_features: &'a WorkspaceFeatures,
+        target_cfg: &Cfg,
+        resolver: Resolver,
+    ) -> Self {
+        let mut graph = DependencyGraph {
+            metadata,
+            resolver,
+            package_ids: vec![root.id.repr.clone()],
+            parents: HashMap::new(),
+            features: HashMap::new(),
+            resolver1_only_features: HashMap::new(),
+            workspace_features,
+        };
+
+        let resolver1_features = requested_features(
+            metadata,
+            root,
+            root_features.clone(),
+            target_cfg,
+            Resolver::V1,
+        );
+        let resolver2_features =
+            requested_features(metadata, root, root_features, target_cfg, Resolver::V2);
+        for (package_id, features) in &resolver1_features {
+            let package = metadata.find_package(package_id).unwrap();
+            let resolver2_feature_names: Vec<_> = package
+                .active_features_for_features(
+                    resolver2_features
+                        .get(package_id)
+                        .map_or(&[], |features| features.as_slice()),
+                )
+                .into_iter()
+                .map(|n| n.name)
+                .collect();
+            let resolver1_only_features = package
+                .active_features_for_features(features)
+                .into_iter()
+                .filter(|n| !resolver2_feature_names.contains(&n.name))
+                .collect();
+            graph
+                .resolver1_only_features
+                .insert(package_id.clone(), resolver1_only_features);
+        }
+        graph.features = match resolver {
+            Resolver::V1 => resolver1_features,
+            Resolver::V2 => resolver2_features,
+        };
+
+        let mut queue = VecDeque::new();
+        queue.push_back(root.id.repr.clone());
+        while let Some(package_id) = queue.pop_front() {
+            let package = metadata.find_package(&package_id).unwrap();
+            if package.is_proc_macro() {
+                continue;
+            }
+            // the resolve graph also contains optional dependencies that are only enabled via
+            // weak features like "serde?/std", so the dependencies are derived from the features
+            let dependencies = package
+                .active_dependencies(&graph.active_features(package), DependencyKind::Normal)
+                .into_iter()
+                .filter(|dependency| {
+                    dependency
+                        .target
+                        .as_ref()
+                        .is_none_or(|platform| target_cfg.matches_platform(&platform.to_string()))
+                });
+            for dependency in dependencies {
+                let dep_package_id = match metadata.dependency_package_id(package, &dependency) {
+                    Some(dep_package_id) => dep_package_id,
+                    None => continue,
+                };
+                if graph.package_ids.contains(&dep_package_id) {
+                    continue;
+                }
+                graph.package_ids.push(dep_package_id.clone());
+                graph
+                    .parents
+                    .insert(dep_package_id.clone(), package_id.clone());
+                queue.push_back(dep_package_id);
+            }
+        }
+        graph
+    }
+
+    /// Packages that are linked into the root in the order they were discovered, starting with
+    /// the root.
+    pub fn packages(&self) -> Vec<&'a Package> {
+        self.package_ids
+            .iter()
+            .map(|package_id| self.metadata.find_package(package_id).unwrap())
+            .collect()
+    }
+
+    /// Features that are requested for the package, not including the ones that are activated
+    /// by those features inside of the package.
+    pub fn features(&self, package_id: &str) -> &[Feature] {
+        self.features
+            .get(package_id)
+            .map_or(&[], |features| features.as_slice())
+    }
+
+    /// Features of the package as resolved by cargo, together with the causes that lead to their
+    /// activation.
+    ///
+    /// `cargo metadata` unifies the features of all dependency kinds, even with resolver 2, so
+    /// the features that are only requested via build or dev dependencies are left out then.
+    /// It also unifies the features of all workspace members, so the features that are only
+    /// requested by other members are left out as well.
+    pub fn active_features(&self, package: &Package) -> Vec<Feature> {
+        let explained_features =
+            package.active_features_for_features(self.features(&package.id.repr));
+        let resolver1_only_features = self.resolver1_only_features(&package.id.repr);
+        self.metadata
+            .resolved_features(&package.id.repr)
+            .iter()
+            .filter(|name| {
+                self.resolver == Resolver::V1
+                    || !resolver1_only_features.iter().any(|n| &n.name == *name)
+            })
+            // features that no member requests are kept, as the model might be incomplete
+            .filter(|name| {
+                explained_features.iter().any(|n| &n.name == *name)
+                    || !self.workspace_features.contains(&package.id.repr, name)
+            })
+            .map(|name| {
+                explained_features
+                    .iter()
+                    .find(|n| &n.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| Feature::new(package.id.repr.clone(), name.clone()))
+            })
+            .collect()
+    }
+
+    /// Compares the features that the model derives for each package with the ones resolved by
+    /// cargo, and returns the packages for which they differ.
+    ///
+    /// As `cargo metadata` unifies the features of all dependency kinds, it is compared against
+    /// the model of resolver 1. Features that are requested by other workspace members are not
+    /// reported.
+    pub fn verify_model(&self) -> Vec<FeatureModelDifference<'a>> {
+        let mut differences = vec![];
+        for package in self.packages() {
+            let mut modeled_features: Vec<_> = package
+                .active_features_for_features(self.features(&package.id.repr))
+                .into_iter()
+                .chain(
+                    self.resolver1_only_features(&package.id.repr)
+                        .iter()
+                        .cloned(),
+                )
+                .map(|n| n.name)
+                // cargo only reports the features of the package itself, and not e.g.
+                // "serde/std" or a "default" feature that isn't declared
+                .filter(|name| package.has_feature(name))
+                .collect();
+            modeled_features.sort();
+            modeled_features.dedup();
+            let resolved_features = self.metadata.resolved_features(&package.id.repr);
+
+            let mut unexplained_features: Vec<_> = resolved_features
+                .iter()
+                .filter(|n| {
+                    !modeled_features.contains(n)
+                        && !self.workspace_features.contains(&package.id.repr, n)
+                })
+                .cloned()
+                .collect();
+            unexplained_features.sort();
+            let unresolved_features: Vec<_> = modeled_features
+                .into_iter()
+                .filter(|n| !resolved_features.contains(n))
+                .collect();
+            if !unexplained_features.is_empty() || !unresolved_features.is_empty() {
+                differences.push(FeatureModelDifference {
+                    package,
+                    unexplained_features,
+                    unresolved_features,
+                });
+            }
+        }
+        differences
+    }
+
+    /// Active features of the package that would not be active with resolver 2, as they are only
+    /// requested via build dependencies, dev dependencies or dependencies for other platforms.
+    pub fn resolver1_only_features(&self, package_id: &str) -> &[Feature] {
+        self.resolver1_only_features
+            .get(package_id)
+            .map_or(&[], |features| features.as_slice())
+    }
+
+    /// Path of packages that leads from the root to the package, including both of them.
+    pub fn path_to(&self, package_id: &str) -> Vec<&'a Package> {
+        let mut path = vec![self.metadata.find_package(package_id).unwrap()];
+        let mut current = package_id;
+        while let Some(parent) = self.parents.get(current) {
+            path.push(self.metadata.find_package(parent).unwrap());
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Features that the root (via `root_features`) and the dependents request for each package.
+///
+/// As packages are only linked once, the features that all dependents request for a package are
+/// unified, and propagated further until they don't change anymore.
+///
+/// With resolver 1, the features requested via build dependencies, dev dependencies of the root,
+/// dependencies of proc macros and dependencies for other platforms are unified with the ones of
+/// normal dependencies.
+fn requested_features(
+    metadata: &CargoMetadata,
+    root: &Package,
+    root_features: Vec<Feature>,
+    target_cfg: &Cfg,
+    resolver: Resolver,
+) -> HashMap<String, Vec<Feature>> {
+    let mut features = HashMap::new();
+    features.insert(root.id.repr.clone(), vec![]);
+    add_features(&mut features, &root.id.repr, root_features);
+    let mut linked_package_ids = HashSet::new();
+    linked_package_ids.insert(root.id.repr.clone());
+
+    let mut queue = VecDeque::new();
+    queue.push_back(root.id.repr.clone());
+    while let Some(package_id) = queue.pop_front() {
+        let package = metadata.find_package(&package_id).unwrap();
+        // packages that are only built for the host (build dependencies, proc macros and
+        // their dependencies) are only walked to unify their features with resolver 1
+        let is_linked = linked_package_ids.contains(&package_id) && !package.is_proc_macro();
+        if !is_linked && resolver == Resolver::V2 {
+            continue;
+        }
+        let active_features = package.active_features_for_features(&features[&package_id]);
+
+        let mut dependencies: Vec<_> = package
+            .active_dependencies(&active_features, DependencyKind::Normal)
+            .into_iter()
+            .map(|dependency| (dependency, is_linked))
+            .collect();
+        if resolver == Resolver::V1 {
+            let build_dependencies =
+                package.active_dependencies(&active_features, DependencyKind::Build);
+            dependencies.extend(build_dependencies.into_iter().map(|n| (n, false)));
+            // dev dependencies are only built for the package that is checked
+            if package_id == root.id.repr {
+                let dev_dependencies =
+                    package.active_dependencies(&active_features, DependencyKind::Development);
+                dependencies.extend(dev_dependencies.into_iter().map(|n| (n, false)));
+            }
+        }
+
+        for (dependency, mut dependency_is_linked) in dependencies {
+            let applies_to_target = dependency
+                .target
+                .as_ref()
+                .is_none_or(|platform| target_cfg.matches_platform(&platform.to_string()));
+            if !applies_to_target {
+                // dependencies for other platforms are only walked to unify their features
+                // with resolver 1
+                match resolver {
+                    Resolver::V1 => dependency_is_linked = false,
+                    Resolver::V2 => continue,
+                }
+            }
+            let dep_package_id = match metadata.dependency_package_id(package, &dependency) {
+                Some(dep_package_id) => dep_package_id,
+                None => continue,
+            };
+            let is_new = !features.contains_key(&dep_package_id);
+            if is_new {
+                features.insert(dep_package_id.clone(), vec![]);
+            }
+            let is_newly_linked =
+                dependency_is_linked && linked_package_ids.insert(dep_package_id.clone());
+
+            let dependency_features =
+                package.dependency_features(metadata, &dependency, &active_features);
+            let has_new_features =
+                add_features(&mut features, &dep_package_id, dependency_features);
+            // revisit already walked packages, so that the new features reach their
+            // dependencies as well
+            if is_new || is_newly_linked || has_new_features {
+                queue.push_back(dep_package_id);
+            }
+        }
+    }
+    features
+}
+
+/// Adds the features that are not yet requested for the package and returns whether there were
+/// any. Only the first cause of a feature is kept.
+fn add_features(
+    features: &mut HashMap<String, Vec<Feature>>,
+    package_id: &str,
+    new_features: Vec<Feature>,
+) -> bool {
+    let package_features = features.get_mut(package_id).unwrap();
+    let mut has_new_features = false;
+    for feature in new_features {
+        if !package_features.iter().any(|n| n.name == feature.name) {
+            package_features.push(feature);
+            has_new_features = true;
+        }
+    }
+    has_new_features
+}
@@ -1,5 +1,8 @@
-use cargo_metadata::{Dependency, DependencyKind, Metadata, Package};
-use std::collections::HashSet;
+use cargo_metadata::{Dependency, DependencyKind, Node, Package};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use crate::metadata::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Feature {
@@ -33,7 +36,14 @@ impl Feature {
         }
     }
 
-    pub fn print(&self, metadata: &Metadata, offset: usize) {
+    /// Writes the feature together with the chain of causes that activated it, indented by
+    /// `offset` levels.
+    pub fn write(
+        &self,
+        out: &mut impl fmt::Write,
+        metadata: &CargoMetadata,
+        offset: usize,
+    ) -> fmt::Result {
         let package_print_name = |package_id| {
             let package = metadata.find_package(package_id);
             if package.is_none() {
@@ -42,27 +52,27 @@ impl Feature {
             let package = package.unwrap();
             format!("{}:{}", package.name, package.version)
         };
-        for _ in 0..offset {
-            print!("  ");
-        }
-        println!(
+        write!(out, "{}", "  ".repeat(offset))?;
+        writeln!(
+            out,
             "- Caused by feature flag \"{}\" in crate \"{}\"",
             self.name,
             package_print_name(&self.package_id)
-        );
+        )?;
         for cause in self.causes.iter() {
-            cause.print(metadata, offset + 1);
+            cause.write(out, metadata, offset + 1)?;
         }
+        Ok(())
     }
 }
 
 impl FeatureCause {
-    pub fn print(&self, metadata: &Metadata, offset: usize) {
-        let print_offset = || {
-            for _ in 0..offset {
-                print!("  ");
-            }
-        };
+    pub fn write(
+        &self,
+        out: &mut impl fmt::Write,
+        metadata: &CargoMetadata,
+        offset: usize,
+    ) -> fmt::Result {
         let package_print_name = |package_id| {
             let package = metadata.find_package(package_id);
             if package.is_none() {
@@ -71,35 +81,71 @@ impl FeatureCause {
             let package = package.unwrap();
             format!("{}:{}", package.name, package.version)
         };
+        let print_offset = "  ".repeat(offset);
         match self {
-            FeatureCause::Feature(feat) => feat.print(metadata, offset),
-            FeatureCause::CliFlag(flag) => {
-                print_offset();
-                println!("- Caused by providing CLI --features flag \"{}\"", flag)
-            }
-            FeatureCause::Default(package_id) => {
-                print_offset();
-                println!(
-                    "- Caused by implicitly enabled default feature from \"{}\"",
-                    package_print_name(package_id)
-                )
-            }
-            FeatureCause::Explicit(package_id) => {
-                print_offset();
-                println!(
-                    "- Explicityly enabled feature from \"{}\"",
-                    package_print_name(package_id)
-                )
+            FeatureCause::Feature(feat) => feat.write(out, metadata, offset),
+            FeatureCause::CliFlag(flag) => writeln!(
+                out,
+                "{}- Caused by providing CLI --features flag \"{}\"",
+                print_offset, flag
+            ),
+            FeatureCause::Default(package_id) => writeln!(
+                out,
+                "{}- Caused by implicitly enabled default feature from \"{}\"",
+                print_offset,
+                package_print_name(package_id)
+            ),
+            FeatureCause::Explicit(package_id) => writeln!(
+                out,
+                "{}- Explicityly enabled feature from \"{}\"",
+                print_offset,
+                package_print_name(package_id)
+            ),
+        }
+    }
+}
+
+/// An entry of a `[features]` table, e.g. `std = ["alloc", "dep:libc", "serde?/std"]`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FeatureValue<'a> {
+    /// `alloc`: a feature of the package itself, or the implicit feature of an optional
+    /// dependency.
+    Feature(&'a str),
+    /// `dep:libc`: activates an optional dependency.
+    Dependency(&'a str),
+    /// `serde/std` activates the (optional) dependency and its feature, while the weak
+    /// `serde?/std` only activates the feature if the dependency is activated otherwise.
+    DependencyFeature {
+        dependency: &'a str,
+        feature: &'a str,
+        weak: bool,
+    },
+}
+
+impl<'a> FeatureValue<'a> {
+    pub fn parse(value: &'a str) -> Self {
+        if let Some(dependency) = value.strip_prefix("dep:") {
+            return FeatureValue::Dependency(dependency);
+        }
+        match value.find('/') {
+            Some(separator) => {
+                let dependency = &value[..separator];
+                FeatureValue::DependencyFeature {
+                    dependency: dependency.trim_end_matches('?'),
+                    feature: &value[separator + 1..],
+                    weak: dependency.ends_with('?'),
+                }
             }
+            None => FeatureValue::Feature(value),
         }
     }
 }
 
 pub trait PackageExt {
     /// Receives a list of activated features (features activated by other features have already
-    /// been calculate), and should return a list of dependencies that are activated for that
-    /// featureset.
-    fn active_dependencies(&self, features: &[Feature]) -> Vec<Dependency>;
+    /// been calculate), and should return a list of dependencies of the provided kind that are
+    /// activated for that featureset.
+    fn active_dependencies(&self, features: &[Feature], kind: DependencyKind) -> Vec<Dependency>;
 
     fn always_on_dependencies(&self) -> Vec<Dependency>;
     /// Active dependencies for a single feature.
@@ -117,40 +163,28 @@ pub trait PackageExt {
         resolved_features.into_iter().collect()
     }
 
-    /// Tries to turn a feature like "serde/std" into a feature flag on "serde".
-    fn dependency_feature_for_feature(
+    /// Features that are activated on an active dependency, via the dependency declaration
+    /// itself and via features like "serde/std" or "serde?/std" in the provided active features.
+    fn dependency_features(
         &self,
-        metadata: &Metadata,
-        feature: &Feature,
-    ) -> Option<Feature>;
-
-    fn dependency_features_for_features(
-        &self,
-        metadata: &Metadata,
-        features: &[Feature],
-    ) -> Vec<Feature> {
-        features
-            .iter()
-            .filter_map(|feature| self.dependency_feature_for_feature(metadata, feature))
-            .collect()
-    }
-
-    fn all_dependency_features(
-        &self,
-        metadata: &Metadata,
-        external_features: &[Feature],
-    ) -> Vec<Feature> {
-        let mut features = self.fixed_dependency_features(metadata);
-        for feat in self.dependency_features_for_features(metadata, external_features) {
-            features.push(feat);
-        }
-
-        features
-    }
+        metadata: &CargoMetadata,
+        dependency: &Dependency,
+        active_features: &[Feature],
+    ) -> Vec<Feature>;
 
     /// Fixed dependency features. Those are hardcoded in the Cargo.toml of the package and can not
     /// be deactivated by turning off its default features.
-    fn fixed_dependency_features(&self, metadata: &Metadata) -> Vec<Feature>;
+    fn fixed_dependency_features(
+        &self,
+        dependency: &Dependency,
+        dep_package_id: &str,
+    ) -> Vec<Feature>;
+
+    /// Whether an optional dependency has an implicit feature of the same name.
+    fn has_implicit_feature(&self, dependency_name: &str) -> bool;
+    /// Whether `name` is a feature of the package, i.e. it is declared in the `[features]` table
+    /// or is the implicit feature of an optional dependency.
+    fn has_feature(&self, name: &str) -> bool;
 
     fn lib_target_sources(&self) -> Vec<String>;
     fn bin_target_sources(&self) -> Vec<String>;
@@ -159,7 +193,7 @@ pub trait PackageExt {
 }
 
 impl PackageExt for Package {
-    fn active_dependencies(&self, features: &[Feature]) -> Vec<Dependency> {
+    fn active_dependencies(&self, features: &[Feature], kind: DependencyKind) -> Vec<Dependency> {
         let mut dependencies = Vec::new();
         for dep in self.always_on_dependencies() {
             dependencies.push(dep);
@@ -171,11 +205,14 @@ impl PackageExt for Package {
                 dependencies.push(dep);
             }
         }
-        dependencies.dedup_by(|a, b| a.name == b.name);
-        dependencies = dependencies
-            .into_iter()
-            .filter(|dep| dep.kind == DependencyKind::Normal)
-            .collect();
+        dependencies.retain(|dep| dep.kind == kind);
+        // a dependency can be declared for all platforms and for a specific one with different
+        // features, so only repeated declarations are removed
+        let mut declarations = HashSet::new();
+        dependencies.retain(|dep| {
+            let target = dep.target.as_ref().map(|platform| platform.to_string());
+            declarations.insert((dep.local_name().to_owned(), target))
+        });
 
         dependencies
     }
@@ -186,125 +223,156 @@ impl PackageExt for Package {
         self.dependencies
             .iter()
             .filter(|dependency| {
-                for feature in activated_features.iter() {
-                    if feature.name == dependency.name {
-                        return true;
-                    }
-                }
-                return false;
+                let name = dependency.local_name();
+                let has_implicit_feature = self.has_implicit_feature(name);
+                activated_features
+                    .iter()
+                    .any(|feature| match FeatureValue::parse(&feature.name) {
+                        FeatureValue::Feature(feature) => has_implicit_feature && feature == name,
+                        FeatureValue::Dependency(dependency) => dependency == name,
+                        FeatureValue::DependencyFeature {
+                            dependency, weak, ..
+                        } => dependency == name && !weak,
+                    })
             })
             .map(|n| n.to_owned())
             .collect()
     }
 
     fn active_features_for_feature(&self, feature: &Feature) -> Vec<Feature> {
-        let mut resolved_features: HashSet<Feature> = HashSet::new();
-        let mut unresolved_features: HashSet<Feature> = HashSet::new();
-        unresolved_features.insert(feature.to_owned());
-
-        while !unresolved_features.is_empty() {
-            for unresolved in unresolved_features.clone().iter() {
-                let activated_features: Vec<Feature> = self
-                    .features
-                    .get(&unresolved.name)
-                    .map(|features| {
-                        features
-                            .clone()
-                            .into_iter()
-                            .map(|raw_feature| {
-                                let mut new_feature = Feature::new(self.id.repr.clone(), raw_feature);
-                                new_feature
-                                    .causes
-                                    .push(FeatureCause::Feature(Box::new(feature.clone())));
-
-                                new_feature
-                            })
-                            .collect()
-                    })
-                    .unwrap_or_default();
-                unresolved_features.remove(&unresolved);
-                resolved_features.insert(unresolved.to_owned());
-                for activated in activated_features {
-                    if !resolved_features.contains(&activated) {
-                        unresolved_features.insert(activated);
+        let mut resolved_features: Vec<Feature> = Vec::new();
+        let mut unresolved_features: VecDeque<Feature> = VecDeque::new();
+        unresolved_features.push_back(feature.to_owned());
+
+        while let Some(unresolved) = unresolved_features.pop_front() {
+            if resolved_features.iter().any(|n| n.name == unresolved.name) {
+                continue;
+            }
+            // only plain features have entries in the `[features]` table, `dep:serde`,
+            // `serde/std` and `serde?/std` are resolved against the dependencies
+            match FeatureValue::parse(&unresolved.name) {
+                FeatureValue::Feature(name) => {
+                    for raw_feature in self.features.get(name).cloned().unwrap_or_default() {
+                        let mut new_feature = Feature::new(self.id.repr.clone(), raw_feature);
+                        new_feature
+                            .causes
+                            .push(FeatureCause::Feature(Box::new(unresolved.clone())));
+                        unresolved_features.push_back(new_feature);
                     }
                 }
+                // `serde/std` also activates the implicit `serde` feature of an optional
+                // dependency
+                FeatureValue::DependencyFeature {
+                    dependency,
+                    weak: false,
+                    ..
+                } if self.has_implicit_feature(dependency)
+                    && self
+                        .dependencies
+                        .iter()
+                        .any(|n| n.optional && n.local_name() == dependency) =>
+                {
+                    let mut new_feature = Feature::new(self.id.repr.clone(), dependency.to_owned());
+                    new_feature
+                        .causes
+                        .push(FeatureCause::Feature(Box::new(unresolved.clone())));
+                    unresolved_features.push_back(new_feature);
+                }
+                _ => {}
             }
+            resolved_features.push(unresolved);
         }
 
-        resolved_features.into_iter().collect()
+        resolved_features
     }
 
-    fn dependency_feature_for_feature(
+    fn dependency_features(
         &self,
-        metadata: &Metadata,
-        feature: &Feature,
-    ) -> Option<Feature> {
-        if !feature.name.contains("/") {
-            return None;
-        }
-
-        let dependency_feature_parts: Vec<_> = feature.name.split("/").collect();
-        let dependency_name = dependency_feature_parts[0];
-        let dependency_feature_name = dependency_feature_parts[1];
-        let dependency = self.dependencies.iter().find(|n| n.name == dependency_name);
-        if dependency.is_none() {
-            return None;
-        }
-
-        let dep_package_id = metadata.dependency_package_id(self, dependency.unwrap());
+        metadata: &CargoMetadata,
+        dependency: &Dependency,
+        active_features: &[Feature],
+    ) -> Vec<Feature> {
         // package_id of dependency might not be findable if we try to activate the feature of a
         // optional dependency
-        if dep_package_id.is_none() {
-            return None;
+        let dep_package_id = match metadata.dependency_package_id(self, dependency) {
+            Some(dep_package_id) => dep_package_id,
+            None => return vec![],
+        };
+        let mut features = self.fixed_dependency_features(dependency, &dep_package_id);
+        for feature in active_features {
+            // weak features only apply to active dependencies, which this one is
+            if let FeatureValue::DependencyFeature {
+                dependency: dependency_name,
+                feature: dependency_feature_name,
+                ..
+            } = FeatureValue::parse(&feature.name)
+            {
+                if dependency_name == dependency.local_name() {
+                    let mut new_feature =
+                        Feature::new(dep_package_id.clone(), dependency_feature_name.to_owned());
+                    new_feature
+                        .causes
+                        .push(FeatureCause::Feature(Box::new(feature.clone())));
+                    features.push(new_feature);
+                }
+            }
         }
+        features
+    }
 
-        let mut new_feature =
-            Feature::new(dep_package_id.unwrap(), dependency_feature_name.to_owned());
-        new_feature
-            .causes
-            .push(FeatureCause::Feature(Box::new(feature.clone())));
-
-        Some(new_feature)
+    fn fixed_dependency_features(
+        &self,
+        dependency: &Dependency,
+        dep_package_id: &str,
+    ) -> Vec<Feature> {
+        // features activated via
+        // serde = { version = "*", features = ["std"] }
+        //                                      ^^^^^
+        let mut explicit_dependency_features = dependency
+            .features
+            .clone()
+            .into_iter()
+            .map(|raw_feature| {
+                let mut feature = Feature::new(dep_package_id.to_owned(), raw_feature);
+                feature
+                    .causes
+                    .push(FeatureCause::Explicit(self.id.repr.clone()));
+                feature
+            })
+            .collect::<Vec<_>>();
+        // features activated via
+        // serde = { version = "*", default-features = true }
+        //                                             ^^^^
+        // or the absence of the default-features option
+        if dependency.uses_default_features {
+            let mut feature = Feature::new(dep_package_id.to_owned(), "default".to_owned());
+            feature
+                .causes
+                .push(FeatureCause::Default(self.id.repr.clone()));
+
+            explicit_dependency_features.push(feature);
+        }
+        explicit_dependency_features
     }
 
-    fn fixed_dependency_features(&self, metadata: &Metadata) -> Vec<Feature> {
-        self.dependencies
+    fn has_implicit_feature(&self, dependency_name: &str) -> bool {
+        // optional dependencies that are enabled via `dep:name` don't have an implicit feature.
+        // Newer versions of cargo list the implicit feature itself as `name = ["dep:name"]`.
+        !self
+            .features
             .iter()
-            .flat_map(|dependency| {
-                let dep_package_id = metadata.dependency_package_id(self, dependency);
-                // package_id of dependency might not be findable if we try to activate the feature of a
-                // optional dependency
-                if dep_package_id.is_none() {
-                    return Vec::new();
-                }
-                let dep_package_id = dep_package_id.unwrap();
-                // features activated via
-                // serde = { version = "*", features = ["std"] }
-                //                                      ^^^^^
-                let mut explicit_dependency_features = dependency
-                    .features
-                    .clone()
-                    .into_iter()
-                    .map(|raw_feature| {
-                        let mut feature = Feature::new(dep_package_id.to_owned(), raw_feature);
-                        feature.causes.push(FeatureCause::Explicit(self.id.repr.clone()));
-                        feature
-                    })
-                    .collect::<Vec<_>>();
-                // features activated via
-                // serde = { version = "*", default-features = true }
-                //                                             ^^^^
-                // or the absence of the default-features option
-                if dependency.uses_default_features {
-                    let mut feature = Feature::new(dep_package_id.to_owned(), "default".to_owned());
-                    feature.causes.push(FeatureCause::Default(self.id.repr.clone()));
-
-                    explicit_dependency_features.push(feature);
-                }
-                explicit_dependency_features
+            .filter(|(name, _)| name.as_str() != dependency_name)
+            .flat_map(|(_, values)| values)
+            .any(|n| FeatureValue::parse(n) == FeatureValue::Dependency(dependency_name))
+    }
+
+    fn has_feature(&self, name: &str) -> bool {
+        self.features.contains_key(name)
+            || self.dependencies.iter().any(|dependency| {
+                dependency.optional
+                    && dependency.local_name() == name
+                    && self.has_implicit_feature(name)
             })
-            .collect()
     }
 
     fn always_on_dependencies(&self) -> Vec<Dependency> {
@@ -336,44 +404,72 @@ impl PackageExt for Package {
     fn is_proc_macro(&self) -> bool {
         self.targets
             .iter()
-            .filter(|target| target.kind.contains(&"proc-macro".to_string()))
-            .next()
-            .is_some()
+            .any(|target| target.kind.contains(&"proc-macro".to_string()))
+    }
+}
+
+pub trait DependencyExt {
+    /// Name the dependency is referred to by in its dependent package, e.g. in feature strings.
+    ///
+    /// For `serde_crate = { package = "serde" }` this is `serde_crate`.
+    fn local_name(&self) -> &str;
+}
+
+impl DependencyExt for Dependency {
+    fn local_name(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
     }
 }
 
 pub trait MetadataExt {
     fn find_package(&self, package_id: &str) -> Option<&Package>;
+    fn find_resolve_node(&self, package_id: &str) -> Option<&Node>;
+    /// Features of the package as resolved by cargo for the requested features and platform.
+    fn resolved_features(&self, package_id: &str) -> &[String] {
+        self.find_resolve_node(package_id)
+            .map_or(&[], |node| node.features.as_slice())
+    }
     fn dependency_package_id(&self, package: &Package, dependency: &Dependency) -> Option<String>;
 }
 
-impl MetadataExt for Metadata {
+impl MetadataExt for CargoMetadata {
     fn find_package(&self, package_id: &str) -> Option<&Package> {
         self.packages
             .iter()
             .find(|package| package.id.repr == package_id)
     }
 
-    fn dependency_package_id(&self, package: &Package, dependency: &Dependency) -> Option<String> {
-        let resolve_node = self
-            .resolve
-            .clone()
-            .unwrap()
+    fn find_resolve_node(&self, package_id: &str) -> Option<&Node> {
+        self.resolve
+            .as_ref()?
             .nodes
-            .into_iter()
-            .find(|n| n.id == package.id)
-            .unwrap();
-        // All dependency packages of the package
-        let dependency_packages: Vec<Package> = self
-            .packages
             .iter()
-            .filter(|n| resolve_node.dependencies.contains(&n.id))
-            .map(|n| n.clone())
+            .find(|node| node.id.repr == package_id)
+    }
+
+    fn dependency_package_id(&self, package: &Package, dependency: &Dependency) -> Option<String> {
+        let resolve_node = self.find_resolve_node(&package.id.repr)?;
+        // the resolve graph refers to renamed dependencies by their (library) rename
+        let renamed_name = dependency.rename.as_ref().map(|n| n.replace('-', "_"));
+        // All dependency packages of the package that could belong to the dependency
+        let candidates: Vec<&Package> = resolve_node
+            .deps
+            .iter()
+            .filter(|node_dep| renamed_name.as_ref().is_none_or(|n| &node_dep.name == n))
+            .filter_map(|node_dep| self.find_package(&node_dep.pkg.repr))
+            .filter(|dep_package| dep_package.name == dependency.name)
+            .filter(|dep_package| {
+                self.dep_kinds(&package.id.repr, &dep_package.id.repr)
+                    .is_none_or(|kinds| kinds.iter().any(|kind| kind.matches(dependency)))
+            })
             .collect();
 
-        dependency_packages
-            .into_iter()
-            .find(|package| package.name == dependency.name)
-            .map(|n| n.id.repr)
+        // e.g. `rand 0.7` as a normal and `rand 0.8` as a dev dependency with an old cargo
+        // that doesn't report the dependency kinds
+        candidates
+            .iter()
+            .find(|dep_package| dependency.req.matches(&dep_package.version))
+            .or_else(|| candidates.first())
+            .map(|dep_package| dep_package.id.repr.clone())
     }
 }
@@ -0,0 +1,264 @@
+use serde::Serialize;
+
+use crate::check::*;
+use crate::check_source::*;
+use crate::ext::*;
+use crate::lint::*;
+use crate::report::*;
+use crate::util::*;
+
+/// Document that is printed for `--message-format json`.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    /// Highest tier that is required by any of the checked members.
+    tier: SupportTier,
+    /// Most severe level of the findings in all checked members, which determines the exit code.
+    level: Level,
+    members: Vec<JsonMember<'a>>,
+    /// One record per checked package.
+    packages: Vec<JsonPackage<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonMember<'a> {
+    id: &'a str,
+    name: &'a str,
+    verdict: Verdict,
+    tier: SupportTier,
+    /// Most severe level of the findings in the member and its dependencies.
+    level: Level,
+}
+
+#[derive(Serialize)]
+struct JsonPackage<'a> {
+    id: &'a str,
+    name: &'a str,
+    version: &'a str,
+    verdict: Verdict,
+    tier: SupportTier,
+    /// Most severe level of the findings, `allow` if there are none.
+    level: Level,
+    support: JsonSupport,
+    /// Offenses that prevent the crate from being no_std.
+    offenses: Vec<JsonOffense<'a>>,
+    /// Offenses that are ignored, as they are compiled out for the active configuration.
+    compiled_out_offenses: Vec<JsonOffense<'a>>,
+    alloc_usages: Vec<JsonUsage<'a>>,
+    active_features: Vec<JsonFeature<'a>>,
+    /// Active features that would not be active with resolver 2.
+    resolver1_only_features: &'a [String],
+    dependency_path: &'a [String],
+    affected_members: &'a [String],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Verdict {
+    Success,
+    Failure,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonSupport {
+    ConditionalNoStd {
+        condition: String,
+        /// `null` if the condition can not be satisfied by deactivating features.
+        features_to_disable: Option<Vec<String>>,
+    },
+    ProcMacro,
+    SourceOffenses,
+    NoOffenseDetected,
+    /// The source of the crate can not be read or parsed.
+    Unchecked {
+        file: String,
+        /// `null` if the file can not be read.
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct JsonOffense<'a> {
+    kind: &'static str,
+    /// Stable code of the lint, e.g. `NONO0002`.
+    code: &'static str,
+    level: Level,
+    /// `null` for a missing `#![no_std]` attribute.
+    usage: Option<JsonUsage<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonUsage<'a> {
+    path: String,
+    item_path: &'a str,
+    file: String,
+    line: usize,
+    /// 1-based, like in rustc diagnostics.
+    column: usize,
+    /// `#[cfg(...)]` predicates that have to hold for the usage to be compiled.
+    gates: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonFeature<'a> {
+    package_id: &'a str,
+    name: &'a str,
+    causes: Vec<JsonFeatureCause<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonFeatureCause<'a> {
+    /// Activated by another feature.
+    Feature { feature: JsonFeature<'a> },
+    /// Activated as default feature by a dependent.
+    Default { package_id: &'a str },
+    /// Activated via `features = [...]` by a dependent.
+    Explicit { package_id: &'a str },
+    /// Activated via the `--features` flag.
+    CliFlag { flag: &'a str },
+}
+
+impl Serialize for SupportTier {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for Level {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Verdict {
+    /// Only findings that are denied fail the check.
+    fn from_level(level: Level) -> Self {
+        match level {
+            Level::Deny => Verdict::Failure,
+            _ => Verdict::Success,
+        }
+    }
+}
+
+impl<'a> JsonPackage<'a> {
+    fn new(check: &'a CheckResult) -> Self {
+        let support = match check.support {
+            CrateSupport::ConditionalNoStd { ref condition, .. } => JsonSupport::ConditionalNoStd {
+                condition: condition.to_string(),
+                features_to_disable: check.features_to_disable(),
+            },
+            CrateSupport::ProcMacro => JsonSupport::ProcMacro,
+            CrateSupport::SourceOffenses(_) => JsonSupport::SourceOffenses,
+            CrateSupport::NoOffenseDetected => JsonSupport::NoOffenseDetected,
+            CrateSupport::Unchecked(ref error) => JsonSupport::Unchecked {
+                file: relative_to_current_dir(&error.src_path)
+                    .display()
+                    .to_string(),
+                line: error.line_column.map(|(line, _)| line),
+                column: error.line_column.map(|(_, column)| column),
+                message: error.message.clone(),
+            },
+        };
+        let offenses = match check.support {
+            CrateSupport::ConditionalNoStd { .. } => check.leaked_offenses(),
+            _ => check.active_offenses(),
+        };
+        JsonPackage {
+            id: &check.package_id,
+            name: &check.package_name,
+            version: &check.package_version,
+            verdict: Verdict::from_level(check.max_level()),
+            tier: check.tier(),
+            level: check.max_level(),
+            support,
+            offenses: offenses
+                .into_iter()
+                .map(|offense| JsonOffense::new(check, offense))
+                .collect(),
+            compiled_out_offenses: check
+                .compiled_out_offenses()
+                .into_iter()
+                .map(|offense| JsonOffense::new(check, offense))
+                .collect(),
+            alloc_usages: check
+                .active_alloc_usages()
+                .into_iter()
+                .map(JsonUsage::new)
+                .collect(),
+            active_features: check.active_features.iter().map(JsonFeature::new).collect(),
+            resolver1_only_features: &check.resolver1_only_features,
+            dependency_path: &check.dependency_path,
+            affected_members: &check.affected_members,
+        }
+    }
+}
+
+impl<'a> JsonOffense<'a> {
+    fn new(check: &CheckResult, offense: &'a SourceOffense) -> Self {
+        JsonOffense {
+            kind: offense.lint().name(),
+            code: offense.lint().code(),
+            level: check.level(offense.lint()),
+            usage: offense.std_usage().map(JsonUsage::new),
+        }
+    }
+}
+
+impl<'a> JsonUsage<'a> {
+    fn new(usage: &'a StdUsage) -> Self {
+        let (line, column) = usage.line_column();
+        JsonUsage {
+            path: usage.path(),
+            item_path: usage.item_path(),
+            file: usage.relative_src_path().display().to_string(),
+            line,
+            column,
+            gates: usage.gates().iter().map(|n| n.to_string()).collect(),
+        }
+    }
+}
+
+impl<'a> JsonFeature<'a> {
+    fn new(feature: &'a Feature) -> Self {
+        JsonFeature {
+            package_id: &feature.package_id,
+            name: &feature.name,
+            causes: feature
+                .causes
+                .iter()
+                .map(|cause| match cause {
+                    FeatureCause::Feature(feature) => JsonFeatureCause::Feature {
+                        feature: JsonFeature::new(feature),
+                    },
+                    FeatureCause::Default(package_id) => JsonFeatureCause::Default { package_id },
+                    FeatureCause::Explicit(package_id) => JsonFeatureCause::Explicit { package_id },
+                    FeatureCause::CliFlag(flag) => JsonFeatureCause::CliFlag { flag },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Renders the report for `--message-format json`.
+pub fn report_to_json(report: &Report) -> String {
+    let json_report = JsonReport {
+        tier: report.tier(),
+        level: report.max_level(),
+        members: report
+            .members
+            .iter()
+            .map(|member| JsonMember {
+                id: &member.package_id,
+                name: &member.package_name,
+                verdict: Verdict::from_level(member.level),
+                tier: member.tier,
+                level: member.level,
+            })
+            .collect(),
+        packages: report.checks.iter().map(JsonPackage::new).collect(),
+    };
+    serde_json::to_string_pretty(&json_report).unwrap()
+}
@@ -0,0 +1,100 @@
+use std::fmt::Write;
+
+use crate::lint::*;
+use crate::metadata::*;
+use crate::report::*;
+
+/// Escapes text for use in XML attributes and character data.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders the report for `--message-format junit`, with one test case per checked package.
+///
+/// Packages with denied findings fail, and the message of the failure contains the same details as
+/// the human readable format, i.e. the offenses and the causes of the features that have to be
+/// deactivated. The details of packages with findings that are only warned about are included as
+/// output of the test case.
+pub fn report_to_junit(report: &Report, metadata: &CargoMetadata) -> String {
+    let failures = report
+        .checks
+        .iter()
+        .filter(|check| check.max_level() == Level::Deny)
+        .count();
+
+    let mut out = String::new();
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(
+        out,
+        "<testsuites name=\"cargo-nono\" tests=\"{tests}\" failures=\"{failures}\">",
+        tests = report.checks.len(),
+        failures = failures
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  <testsuite name=\"cargo nono check\" tests=\"{tests}\" failures=\"{failures}\" errors=\"0\">",
+        tests = report.checks.len(),
+        failures = failures
+    )
+    .unwrap();
+    for check in &report.checks {
+        let name = xml_escape(&format!("{}:{}", check.package_name, check.package_version));
+        if check.max_level() == Level::Allow {
+            writeln!(
+                out,
+                "    <testcase name=\"{}\" classname=\"cargo-nono\"/>",
+                name
+            )
+            .unwrap();
+            continue;
+        }
+        writeln!(
+            out,
+            "    <testcase name=\"{}\" classname=\"cargo-nono\">",
+            name
+        )
+        .unwrap();
+        // findings that are only warned about are reported, but don't fail the test case
+        if check.max_level() == Level::Warn {
+            writeln!(
+                out,
+                "      <system-out>{}</system-out>",
+                xml_escape(&check_to_string(check, metadata))
+            )
+            .unwrap();
+            writeln!(out, "    </testcase>").unwrap();
+            continue;
+        }
+        let message = match check.is_main_pkg {
+            true => format!("{} requires `std`", check.package_name),
+            false => format!(
+                "{} requires `std` via {}",
+                check.package_name,
+                check.dependency_path.join(" -> ")
+            ),
+        };
+        writeln!(
+            out,
+            "      <failure type=\"std\" message=\"{}\">{}</failure>",
+            xml_escape(&message),
+            xml_escape(&check_to_string(check, metadata))
+        )
+        .unwrap();
+        writeln!(out, "    </testcase>").unwrap();
+    }
+    writeln!(out, "  </testsuite>").unwrap();
+    write!(out, "</testsuites>").unwrap();
+    out
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Kind of finding that makes a crate require std.
+///
+/// Each lint has a stable name, which is used as rule id in SARIF, and a stable code, which is
+/// shown in diagnostics and can be looked up via `cargo nono explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    MissingNoStdAttribute,
+    UseStdStatement,
+    StdPath,
+    StdMacro,
+    StdFeature,
+    UnsatisfiableNoStdCondition,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::MissingNoStdAttribute,
+        Lint::UseStdStatement,
+        Lint::StdPath,
+        Lint::StdMacro,
+        Lint::StdFeature,
+        Lint::UnsatisfiableNoStdCondition,
+    ];
+
+    /// `use_std_statement`
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::MissingNoStdAttribute => "missing_no_std_attribute",
+            Lint::UseStdStatement => "use_std_statement",
+            Lint::StdPath => "std_path",
+            Lint::StdMacro => "std_macro",
+            Lint::StdFeature => "std_feature",
+            Lint::UnsatisfiableNoStdCondition => "unsatisfiable_no_std_condition",
+        }
+    }
+
+    /// `NONO0002`
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::MissingNoStdAttribute => "NONO0001",
+            Lint::UseStdStatement => "NONO0002",
+            Lint::StdPath => "NONO0003",
+            Lint::StdMacro => "NONO0004",
+            Lint::StdFeature => "NONO0005",
+            Lint::UnsatisfiableNoStdCondition => "NONO0006",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Lint::MissingNoStdAttribute => "Crate has no #![no_std] attribute or simple conditional attribute like #![cfg_attr(not(feature = \"std\"), no_std)].",
+            Lint::UseStdStatement => "Source code contains an explicit `use std::` statement.",
+            Lint::StdPath => "Source code contains a fully qualified `std::` path.",
+            Lint::StdMacro => "Source code invokes a std-only macro.",
+            Lint::StdFeature => "A feature is enabled that makes a dependency require std.",
+            Lint::UnsatisfiableNoStdCondition => "Crate only supports no_std under a condition that can not be achieved by deactivating features.",
+        }
+    }
+
+    /// Long-form description with examples of the problem and its fix, in markdown.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Lint::MissingNoStdAttribute => include_str!("explanations/NONO0001.md"),
+            Lint::UseStdStatement => include_str!("explanations/NONO0002.md"),
+            Lint::StdPath => include_str!("explanations/NONO0003.md"),
+            Lint::StdMacro => include_str!("explanations/NONO0004.md"),
+            Lint::StdFeature => include_str!("explanations/NONO0005.md"),
+            Lint::UnsatisfiableNoStdCondition => include_str!("explanations/NONO0006.md"),
+        }
+    }
+
+    /// Finds a lint by its code (`NONO0002`, case-insensitive) or name (`use_std_statement`).
+    pub fn find(code_or_name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| {
+            lint.code().eq_ignore_ascii_case(code_or_name) || lint.name() == code_or_name
+        })
+    }
+}
+
+/// How findings of a lint are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Findings are not reported.
+    Allow,
+    /// Findings are reported, but don't fail the check.
+    Warn,
+    /// Findings are reported and fail the check. This is the default for all lints.
+    Deny,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Levels of the lints, for all crates and overridden for single crates.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, Level>,
+    /// crate name -> levels that override the ones for all crates
+    crate_levels: HashMap<String, HashMap<Lint, Level>>,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn set_for_crate(&mut self, crate_name: &str, lint: Lint, level: Level) {
+        self.crate_levels
+            .entry(crate_name.to_owned())
+            .or_default()
+            .insert(lint, level);
+    }
+
+    /// Level of the lint for the crate with the provided name.
+    pub fn level(&self, lint: Lint, crate_name: &str) -> Level {
+        self.crate_levels
+            .get(crate_name)
+            .and_then(|levels| levels.get(&lint))
+            .or_else(|| self.levels.get(&lint))
+            .copied()
+            .unwrap_or(Level::Deny)
+    }
+
+    /// Levels of all lints for the crate with the provided name.
+    pub fn for_crate(&self, crate_name: &str) -> HashMap<Lint, Level> {
+        Lint::ALL
+            .iter()
+            .map(|&lint| (lint, self.level(lint, crate_name)))
+            .collect()
+    }
+}
@@ -1,119 +1,175 @@
+mod cfg;
 mod check;
 mod check_source;
+mod dependency_graph;
 mod ext;
+mod json;
+mod junit;
+mod lint;
+mod metadata;
+mod module_tree;
+mod report;
+mod sarif;
 mod util;
 
 use clap::{App, Arg, SubCommand};
 use console::Emoji;
 use std::path::PathBuf;
 
+use crate::cfg::*;
 use crate::check::*;
 use crate::check_source::*;
+use crate::dependency_graph::*;
 use crate::ext::*;
+use crate::json::*;
+use crate::junit::*;
+use crate::lint::*;
+use crate::report::*;
+use crate::sarif::*;
 use crate::util::*;
 
-use cargo_metadata::{Metadata, Package};
+use cargo_metadata::Package;
 
 pub static SUCCESS: Emoji = Emoji("✅  ", "SUCCESS");
 pub static FAILURE: Emoji = Emoji("❌  ", "FAILURE");
 pub static MAYBE: Emoji = Emoji("❓  ", "MAYBE");
 
-fn check_and_print_package(
+/// Flags that set the level of a lint, like the ones of rustc.
+const LEVEL_FLAGS: &[(&str, &str, Level)] = &[
+    ("allow", "A", Level::Allow),
+    ("warn", "W", Level::Warn),
+    ("deny", "D", Level::Deny),
+];
+
+/// A package that is checked once for all workspace members that link it with the same active
+/// features.
+struct PackageCheck<'a> {
+    package: &'a Package,
+    /// Graph of the first member that links the package.
+    graph: &'a DependencyGraph<'a>,
+    is_main_pkg: bool,
+    feature_names: Vec<String>,
+    /// Indices of the members that link the package.
+    member_indices: Vec<usize>,
+}
+
+fn check_package(
     package: &Package,
-    resolved_dependency_features: &[Feature],
-    metadata: &Metadata,
-    metadata_full: &Metadata,
+    graph: &DependencyGraph,
+    target_cfg: &Cfg,
+    include_tests: bool,
     is_main_pkg: bool,
-) -> bool {
-    let mut package_did_fail = false;
-
-    let package_features: Vec<Feature> = resolved_dependency_features
-        .iter()
-        .filter(|n| n.package_id == package.id.repr)
-        .map(|n| n.to_owned())
-        .collect();
-    let active_features = package.active_features_for_features(&package_features);
-    let active_dependencies = package.active_dependencies(&active_features);
-    let _active_packages = dependencies_to_packages(&package, &metadata_full, &active_dependencies);
-    let _resolved_dependency_features =
-        package.all_dependency_features(&metadata_full, &active_features);
+    affected_members: Vec<String>,
+    lint_levels: &LintLevels,
+) -> CheckResult {
+    let active_features = graph.active_features(package);
 
     let mut support = CrateSupport::NoOffenseDetected;
+    let mut alloc_usages = vec![];
+    let mut src_path = None;
     if package.is_proc_macro() {
         support = CrateSupport::ProcMacro;
     }
     if support == CrateSupport::NoOffenseDetected {
-        match is_main_pkg {
-            false => {
-                let srcs: Vec<_> = package
-                    .lib_target_sources()
-                    .into_iter()
-                    .map(PathBuf::from)
-                    .collect();
-                // TODO: check more than one
-                support = srcs
-                    .into_iter()
-                    .map(|src_path| get_crate_support_from_source(&src_path))
-                    .next()
-                    .unwrap_or(CrateSupport::NoOffenseDetected);
-            }
-            true => {
-                let srcs: Vec<_> = package
-                    .bin_target_sources()
-                    .into_iter()
-                    .chain(package.lib_target_sources())
-                    .map(PathBuf::from)
-                    .collect();
-                support = srcs
-                    .into_iter()
-                    .map(|src_path| get_crate_support_from_source(&src_path))
-                    .next()
-                    .unwrap_or(CrateSupport::NoOffenseDetected);
+        let srcs: Vec<_> = match is_main_pkg {
+            false => package
+                .lib_target_sources()
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            true => package
+                .bin_target_sources()
+                .into_iter()
+                .chain(package.lib_target_sources())
+                .map(PathBuf::from)
+                .collect(),
+        };
+        // TODO: check more than one
+        src_path = srcs.into_iter().next();
+        if let Some(src_path) = &src_path {
+            match get_crate_support_from_source(src_path, target_cfg, include_tests) {
+                Ok(source_check) => {
+                    support = source_check.support;
+                    alloc_usages = source_check.alloc_usages;
+                }
+                Err(error) => support = CrateSupport::Unchecked(error),
             }
         }
     }
 
-    let check = CheckResult {
+    CheckResult {
+        package_id: package.id.repr.clone(),
         package_name: package.name.clone(),
+        package_version: package.version.to_string(),
+        src_path,
         support,
-        active_features: active_features,
-    };
-
-    // set flag that at least one crate check failed
-    if !check.no_std_itself() {
-        package_did_fail = true;
-    }
-    let overall_res = match check.no_std_itself() {
-        true => SUCCESS,
-        false => FAILURE,
-    };
-    println!("{}: {}", check.package_name, overall_res);
-    if check.no_std_itself() {
-        return package_did_fail;
-    }
-    if let CrateSupport::OnlyWithoutFeature(feature) = &check.support {
-        println!(
-            "  - Crate supports no_std if \"{}\" feature is deactivated.",
-            feature
-        );
-        let feat = check.find_active_feature_by_name(&feature).unwrap();
-        feat.print(&metadata, 2);
+        active_features,
+        target_cfg: target_cfg.clone(),
+        include_tests,
+        alloc_usages,
+        is_main_pkg,
+        dependency_path: graph
+            .path_to(&package.id.repr)
+            .iter()
+            .map(|n| format!("{}:{}", n.name, n.version))
+            .collect(),
+        affected_members,
+        resolver1_only_features: graph
+            .resolver1_only_features(&package.id.repr)
+            .iter()
+            .map(|n| n.name.clone())
+            .collect(),
+        lint_levels: lint_levels.for_crate(&package.name),
     }
-    if let CrateSupport::SourceOffenses(ref offenses) = check.support {
-        for offense in offenses {
-            match offense {
-                SourceOffense::MissingNoStdAttribute => {
-                    println!("  - Did not find a #![no_std] attribute or a simple conditional attribute like #![cfg_attr(not(feature = \"std\"), no_std)] in the crate source. Crate most likely doesn't support no_std without changes.");
-                }
-                SourceOffense::UseStdStatement(stmt) => {
-                    println!("  - Source code contains an explicit `use std::` statement.");
-                    println!("{}", stmt);
-                }
+}
+
+/// Prints the packages for which the feature model diverges from cargo's resolve, whose
+/// explanations of feature activations can not be trusted, and exits.
+fn verify_model_and_print(graphs: &[DependencyGraph]) -> ! {
+    let mut package_ids = vec![];
+    let mut differences = vec![];
+    for graph in graphs {
+        for package in graph.packages() {
+            if !package_ids.contains(&package.id) {
+                package_ids.push(package.id.clone());
+            }
+        }
+        for difference in graph.verify_model() {
+            if !differences
+                .iter()
+                .any(|n: &FeatureModelDifference| n.package.id == difference.package.id)
+            {
+                differences.push(difference);
             }
         }
     }
+    for difference in &differences {
+        let package = difference.package;
+        println!("{}:{}: {}", package.name, package.version, FAILURE);
+        for feature in &difference.unexplained_features {
+            println!(
+                "  - Cargo activates the \"{}\" feature, but cargo-nono can not explain why.",
+                feature
+            );
+        }
+        for feature in &difference.unresolved_features {
+            println!(
+                "  - cargo-nono activates the \"{}\" feature, but Cargo doesn't.",
+                feature
+            );
+        }
+    }
 
-    package_did_fail
+    if !differences.is_empty() {
+        println!();
+        println!("Feature model diverges from Cargo's resolve for {} of {} crates, so the printed causes of their features may be misleading.", differences.len(), package_ids.len());
+        std::process::exit(1);
+    }
+    println!(
+        "Feature model matches Cargo's resolve for all {} crates.",
+        package_ids.len()
+    );
+    std::process::exit(0);
 }
 
 fn main() {
@@ -128,70 +184,264 @@ fn main() {
                         .multiple(true)
                         .takes_value(true),
                 )
-                .arg(Arg::with_name("package").long("package").takes_value(true)),
-        );
+                .arg(Arg::with_name("package").long("package").takes_value(true))
+                .arg(
+                    Arg::with_name("manifest-path")
+                        .long("manifest-path")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .conflicts_with("package"),
+                )
+                .arg(
+                    Arg::with_name("exclude")
+                        .long("exclude")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true)
+                        .requires("workspace"),
+                )
+                .arg(Arg::with_name("include-tests").long("include-tests"))
+                .arg(Arg::with_name("target").long("target").takes_value(true))
+                .arg(Arg::with_name("verify-model").long("verify-model"))
+                .args(
+                    &LEVEL_FLAGS
+                        .iter()
+                        .map(|(name, short, _)| {
+                            Arg::with_name(name)
+                                .short(short)
+                                .long(name)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .takes_value(true)
+                                .value_name("LINT")
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .arg(
+                    Arg::with_name("message-format")
+                        .long("message-format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json", "sarif", "junit"])
+                        .default_value("human"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("explain").arg(Arg::with_name("code").required(true)));
 
     let matches = app.clone().get_matches();
     if let Some(matches) = matches.subcommand_matches("check") {
-        let metadata_full = metadata_run(Some("--all-features".to_owned())).unwrap();
-        let metadata = metadata_run(None).unwrap();
-
-        let target_workspace_member =
-            main_ws_member_from_args(&metadata, matches.value_of("package"));
-
-        let target_package = metadata
-            .find_package(&target_workspace_member.repr)
-            .unwrap();
-        let features = features_from_args(
-            target_package.id.repr.clone(),
-            matches.is_present("no-default-features"),
-            matches
-                .values_of("features")
-                .map(|n| n.into_iter().map(|m| m.to_owned()).collect())
-                .unwrap_or(Vec::new())
-                .to_owned(),
-        );
+        let target_cfg = match matches.value_of("target") {
+            Some(target) => match Cfg::target(target) {
+                Some(target_cfg) => target_cfg,
+                None => {
+                    println!("⚠️  Unknown target \"{}\". Please provide a target triple that is known to rustc via --target flag.", target);
+                    std::process::exit(1);
+                }
+            },
+            None => Cfg::host(),
+        };
+        let include_tests = matches.is_present("include-tests");
+        let no_default_features = matches.is_present("no-default-features");
+        let features_args: Vec<String> = matches
+            .values_of("features")
+            .map(|n| n.into_iter().map(|m| m.to_owned()).collect())
+            .unwrap_or_default();
 
-        let active_features = target_package.active_features_for_features(&features);
-        let active_dependencies = target_package.active_dependencies(&active_features);
-        let active_packages =
-            dependencies_to_packages(&target_package, &metadata_full, &active_dependencies);
+        let metadata = metadata_run(&metadata_args_from_args(
+            no_default_features,
+            &features_args,
+            matches.value_of("package"),
+            matches.value_of("manifest-path"),
+            &target_cfg,
+        ))
+        .unwrap();
+        let manifest_dir = manifest_dir_from_args(matches.value_of("manifest-path"));
 
-        let mut package_did_fail = false;
-        let resolved_dependency_features =
-            target_package.all_dependency_features(&metadata_full, &active_features);
+        // like with rustc, later flags override earlier ones
+        let mut level_args = vec![];
+        for (name, _, level) in LEVEL_FLAGS {
+            if let (Some(indices), Some(values)) =
+                (matches.indices_of(name), matches.values_of(name))
+            {
+                level_args.extend(
+                    indices
+                        .zip(values)
+                        .map(|(index, lint)| (index, *level, lint)),
+                );
+            }
+        }
+        level_args.sort_by_key(|(index, _, _)| *index);
+        let level_args: Vec<_> = level_args
+            .into_iter()
+            .map(|(_, level, lint)| (level, lint))
+            .collect();
+        let lint_levels = lint_levels_from_args(&metadata, &level_args);
 
-        let main_package = metadata
-            .packages
-            .iter()
-            .find(|n| &n.id == target_workspace_member)
-            .expect("Unable to find main package.");
-        if check_and_print_package(
-            main_package,
-            &resolved_dependency_features,
+        // like cargo, all members are checked when run on a virtual manifest
+        let is_workspace = matches.is_present("workspace")
+            || (!matches.is_present("package")
+                && is_virtual_workspace_root(&metadata, &manifest_dir));
+        let members: Vec<&Package> = match is_workspace {
+            true => {
+                let excludes: Vec<&str> = matches
+                    .values_of("exclude")
+                    .map(|n| n.collect())
+                    .unwrap_or_default();
+                workspace_members_from_args(&metadata, &excludes)
+            }
+            false => {
+                let target_workspace_member =
+                    main_ws_member_from_args(&metadata, matches.value_of("package"), &manifest_dir);
+                vec![metadata
+                    .find_package(&target_workspace_member.repr)
+                    .unwrap()]
+            }
+        };
+
+        let member_features = |member: &Package| {
+            let member_features_args = match matches.value_of("package") {
+                // features are only passed to the selected package
+                Some(package_name) if package_name != member.name => vec![],
+                _ => features_args.clone(),
+            };
+            features_from_args(
+                member.id.repr.clone(),
+                no_default_features,
+                member_features_args,
+            )
+        };
+        // cargo resolves the features of all members at once, including the excluded ones
+        let workspace_features = WorkspaceFeatures::model(
             &metadata,
-            &metadata_full,
-            true,
-        ) {
-            package_did_fail = true;
+            workspace_members_from_args(&metadata, &[])
+                .into_iter()
+                .map(|member| (member, member_features(member)))
+                .collect(),
+            &target_cfg,
+        );
+        let graphs: Vec<_> = members
+            .iter()
+            .map(|member| {
+                DependencyGraph::walk(
+                    &metadata,
+                    member,
+                    member_features(member),
+                    &workspace_features,
+                    &target_cfg,
+                    metadata.resolver(),
+                )
+            })
+            .collect();
+
+        if matches.is_present("verify-model") {
+            verify_model_and_print(&graphs);
+        }
+
+        // packages that are shared between members are only checked once for each set of
+        // active features
+        let mut checks: Vec<PackageCheck> = vec![];
+        for (member_index, (member, graph)) in members.iter().zip(&graphs).enumerate() {
+            for package in graph.packages() {
+                let is_main_pkg = package.id == member.id;
+                let mut feature_names: Vec<_> = graph
+                    .active_features(package)
+                    .into_iter()
+                    .map(|n| n.name)
+                    .collect();
+                feature_names.sort();
+                let existing_check = checks.iter_mut().find(|check| {
+                    check.package.id == package.id
+                        && check.is_main_pkg == is_main_pkg
+                        && check.feature_names == feature_names
+                });
+                match existing_check {
+                    Some(check) => check.member_indices.push(member_index),
+                    None => checks.push(PackageCheck {
+                        package,
+                        graph,
+                        is_main_pkg,
+                        feature_names,
+                        member_indices: vec![member_index],
+                    }),
+                }
+            }
         }
 
-        for package in active_packages.iter() {
-            if check_and_print_package(
-                package,
-                &resolved_dependency_features,
-                &metadata,
-                &metadata_full,
-                false,
-            ) {
-                package_did_fail = true;
+        let mut member_tiers = vec![SupportTier::Core; members.len()];
+        let mut member_levels = vec![Level::Allow; members.len()];
+        let mut check_results = vec![];
+        for check in &checks {
+            let affected_members: Vec<String> = match is_workspace {
+                true => check
+                    .member_indices
+                    .iter()
+                    .map(|&n| members[n].name.clone())
+                    .collect(),
+                false => vec![],
+            };
+            let check_result = check_package(
+                check.package,
+                check.graph,
+                &target_cfg,
+                include_tests,
+                check.is_main_pkg,
+                affected_members,
+                &lint_levels,
+            );
+            for &member_index in &check.member_indices {
+                member_tiers[member_index] = member_tiers[member_index].max(check_result.tier());
+                member_levels[member_index] =
+                    member_levels[member_index].max(check_result.max_level());
             }
+            check_results.push(check_result);
+        }
+
+        let report = Report {
+            checks: check_results,
+            members: members
+                .iter()
+                .zip(member_tiers)
+                .zip(member_levels)
+                .map(|((member, tier), level)| MemberResult {
+                    package_id: member.id.repr.clone(),
+                    package_name: member.name.clone(),
+                    tier,
+                    level,
+                })
+                .collect(),
+            is_workspace,
+        };
+        match matches.value_of("message-format") {
+            Some("json") => println!("{}", report_to_json(&report)),
+            Some("sarif") => println!("{}", report_to_sarif(&report, &metadata)),
+            Some("junit") => println!("{}", report_to_junit(&report, &metadata)),
+            _ => print_report(&report, &metadata),
         }
-        match package_did_fail {
-            true => std::process::exit(1),
-            false => std::process::exit(0),
+        // the exit code follows the most severe finding that isn't allowed
+        match report.max_level() {
+            Level::Deny => std::process::exit(1),
+            _ => std::process::exit(0),
+        }
+    }
+    if let Some(matches) = matches.subcommand_matches("explain") {
+        let code = matches.value_of("code").unwrap();
+        match Lint::find(code) {
+            Some(lint) => {
+                println!("{}", lint.explanation());
+                std::process::exit(0);
+            }
+            None => {
+                let codes: Vec<_> = Lint::ALL.iter().map(|n| n.code()).collect();
+                println!(
+                    "⚠️  Unknown lint code \"{}\". Please provide one of {:?}.",
+                    code, codes
+                );
+                std::process::exit(1);
+            }
         }
     }
     app.print_help().unwrap();
-    println!(""); // print newline since print_help doesn't do that
+    println!(); // print newline since print_help doesn't do that
 }
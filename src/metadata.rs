@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+
+use cargo_metadata::{Dependency, DependencyKind, Metadata};
+use serde::Deserialize;
+
+/// Output of `cargo metadata`, together with the dependency kinds of the edges in the resolve
+/// graph (`resolve.nodes[].deps[].dep_kinds`), which `cargo_metadata` doesn't expose.
+pub struct CargoMetadata {
+    metadata: Metadata,
+    /// `[workspace.metadata]` table of the root manifest, which `cargo_metadata` doesn't expose.
+    workspace_metadata: serde_json::Value,
+    /// (package id, dependency package id) -> kinds of the edge
+    dep_kinds: HashMap<(String, String), Vec<DepKindInfo>>,
+}
+
+/// Feature resolver of a workspace, see
+/// <https://doc.rust-lang.org/cargo/reference/resolver.html#feature-resolver-version-2>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolver {
+    /// Features of build dependencies, dev dependencies and proc macros are unified with the
+    /// ones of normal dependencies.
+    V1,
+    /// Features are only unified between dependencies that are built for the same target.
+    V2,
+}
+
+/// A single kind of a dependency edge, e.g. a build dependency that only applies to `cfg(unix)`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepKindInfo {
+    /// `null` for normal dependencies, `"dev"` or `"build"` otherwise.
+    kind: Option<String>,
+    /// Platform the dependency is restricted to, e.g. `cfg(unix)`.
+    target: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawMetadata {
+    resolve: Option<RawResolve>,
+    /// Only reported by cargo 1.40 and newer.
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct RawResolve {
+    nodes: Vec<RawNode>,
+}
+
+#[derive(Deserialize)]
+struct RawNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<RawNodeDep>,
+}
+
+#[derive(Deserialize)]
+struct RawNodeDep {
+    pkg: String,
+    /// Only reported by cargo 1.41 and newer.
+    #[serde(default)]
+    dep_kinds: Vec<DepKindInfo>,
+}
+
+impl CargoMetadata {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let metadata = serde_json::from_str(json)?;
+        let raw_metadata: RawMetadata = serde_json::from_str(json)?;
+
+        let mut dep_kinds = HashMap::new();
+        for node in raw_metadata.resolve.map(|n| n.nodes).unwrap_or_default() {
+            for dep in node.deps {
+                dep_kinds.insert((node.id.clone(), dep.pkg), dep.dep_kinds);
+            }
+        }
+        Ok(CargoMetadata {
+            metadata,
+            workspace_metadata: raw_metadata.metadata,
+            dep_kinds,
+        })
+    }
+
+    /// `[workspace.metadata]` table of the root manifest, `null` if there is none.
+    pub fn workspace_metadata(&self) -> &serde_json::Value {
+        &self.workspace_metadata
+    }
+
+    /// Kinds of the edge between the two packages in the resolve graph.
+    ///
+    /// Returns `None` if the edge doesn't exist or if cargo is too old to report the kinds.
+    pub fn dep_kinds(&self, package_id: &str, dep_package_id: &str) -> Option<&[DepKindInfo]> {
+        self.dep_kinds
+            .get(&(package_id.to_owned(), dep_package_id.to_owned()))
+            .filter(|kinds| !kinds.is_empty())
+            .map(|kinds| kinds.as_slice())
+    }
+}
+
+impl CargoMetadata {
+    /// Feature resolver of the workspace, as configured via `resolver = "..."` in the root
+    /// manifest or implied by the edition of the root package.
+    pub fn resolver(&self) -> Resolver {
+        let root_manifest_path = self.workspace_root.join("Cargo.toml");
+        let configured_resolver = fs::read_to_string(&root_manifest_path)
+            .ok()
+            .and_then(|manifest| manifest_resolver(&manifest));
+        match configured_resolver.as_deref() {
+            Some("1") => Resolver::V1,
+            Some(_) => Resolver::V2,
+            // virtual workspaces always default to resolver 1
+            None => {
+                let root_package = self
+                    .packages
+                    .iter()
+                    .find(|package| package.manifest_path == root_manifest_path);
+                match root_package {
+                    Some(package) if package.edition.as_str() >= "2021" => Resolver::V2,
+                    _ => Resolver::V1,
+                }
+            }
+        }
+    }
+}
+
+/// Value of `resolver = "..."` in the `[package]` or `[workspace]` table of a manifest.
+///
+/// Only understands the plain `key = "value"` form, which is enough for the `resolver` key.
+fn manifest_resolver(manifest: &str) -> Option<String> {
+    let mut table = "";
+    for line in manifest.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.starts_with('[') {
+            table = line;
+            continue;
+        }
+        if table != "[package]" && table != "[workspace]" {
+            continue;
+        }
+        let mut key_value = line.splitn(2, '=');
+        if key_value.next().map(|key| key.trim()) == Some("resolver") {
+            let value = key_value.next()?.trim();
+            return Some(value.trim_matches(|c| c == '"' || c == '\'').to_owned());
+        }
+    }
+    None
+}
+
+impl Deref for CargoMetadata {
+    type Target = Metadata;
+
+    fn deref(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl DepKindInfo {
+    /// Whether the edge kind belongs to the dependency declaration in a `Cargo.toml`.
+    pub fn matches(&self, dependency: &Dependency) -> bool {
+        let kind_matches = matches!(
+            (self.kind.as_deref(), &dependency.kind),
+            (None, DependencyKind::Normal)
+                | (Some("dev"), DependencyKind::Development)
+                | (Some("build"), DependencyKind::Build)
+        );
+        let target = dependency.target.as_ref().map(|n| n.to_string());
+        kind_matches && self.target == target
+    }
+}
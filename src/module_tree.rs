@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cfg::*;
+use crate::util::*;
+
+/// A source file that is compiled as part of a crate.
+#[derive(Debug)]
+pub struct ModuleFile {
+    pub src_path: PathBuf,
+    /// Path of the module the file contains, e.g. `["crate", "parser"]` for `src/parser.rs`.
+    pub module_path: Vec<String>,
+    /// Whether the file is the crate root (main.rs / lib.rs).
+    pub is_root: bool,
+    /// `#[cfg(...)]` predicates on the `mod` declarations leading to the file.
+    pub gates: Vec<CfgExpr>,
+    pub syntax: syn::File,
+}
+
+/// A source file of a crate that can not be read or parsed, e.g. because it uses syntax that is
+/// newer than the parser.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SourceError {
+    pub src_path: PathBuf,
+    /// Line and (1-based) column of a parse error.
+    pub line_column: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl SourceError {
+    /// `src/lib.rs:3:5`, relative to the current directory if the file is inside of it.
+    pub fn location(&self) -> String {
+        let src_path = relative_to_current_dir(&self.src_path).display();
+        match self.line_column {
+            Some((line, column)) => format!("{}:{}:{}", src_path, line, column),
+            None => src_path.to_string(),
+        }
+    }
+}
+
+/// A `mod foo;` declaration together with the file it resolves to.
+struct DeclaredModule {
+    src_path: PathBuf,
+    module_path: Vec<String>,
+    gates: Vec<CfgExpr>,
+    /// Files included via `#[path = "..."]` resolve their own submodules like a `mod.rs` file.
+    owns_directory: bool,
+}
+
+/// Directories that `mod` declarations are resolved against while walking a file.
+#[derive(Clone)]
+struct ModuleDirs {
+    /// Base directory for `mod foo;` (-> `foo.rs` or `foo/mod.rs`).
+    module_dir: PathBuf,
+    /// Base directory for `#[path = "..."] mod foo;`.
+    path_attr_dir: PathBuf,
+}
+
+/// Collects all source files that are part of the crate with the provided root file, by following
+/// the `mod` declarations the same way rustc does.
+///
+/// Declared modules whose file can not be found (e.g. because they are generated by a build
+/// script) are skipped, while files that can not be read or parsed fail the whole crate.
+pub fn crate_module_files(root_src_path: &Path) -> Result<Vec<ModuleFile>, SourceError> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let root = DeclaredModule {
+        src_path: root_src_path.to_owned(),
+        module_path: vec!["crate".to_owned()],
+        gates: vec![],
+        owns_directory: true,
+    };
+    collect_module_file(root, true, &mut visited, &mut files)?;
+    Ok(files)
+}
+
+fn collect_module_file(
+    module: DeclaredModule,
+    is_root: bool,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<ModuleFile>,
+) -> Result<(), SourceError> {
+    let DeclaredModule {
+        src_path,
+        module_path,
+        gates,
+        owns_directory,
+    } = module;
+    let canonical_path = src_path.canonicalize().unwrap_or_else(|_| src_path.clone());
+    if !visited.insert(canonical_path) {
+        return Ok(());
+    }
+
+    let src = fs::read_to_string(&src_path).map_err(|err| SourceError {
+        src_path: src_path.clone(),
+        line_column: None,
+        message: err.to_string(),
+    })?;
+    let syntax = syn::parse_file(&src).map_err(|err| {
+        let start = err.span().start();
+        SourceError {
+            src_path: src_path.clone(),
+            // errors at the end of the file don't have a location
+            line_column: Some((start.line, start.column + 1)).filter(|(line, _)| *line > 0),
+            message: err.to_string(),
+        }
+    })?;
+
+    let file_dir = src_path.parent().unwrap().to_owned();
+    // `lib.rs`, `main.rs` and `mod.rs` files own their directory, while `foo.rs` declares its
+    // submodules in `foo/`.
+    let is_mod_rs = owns_directory || src_path.file_name() == Some("mod.rs".as_ref());
+    let module_dir = match is_mod_rs {
+        true => file_dir.clone(),
+        false => file_dir.join(src_path.file_stem().unwrap()),
+    };
+    let dirs = ModuleDirs {
+        module_dir,
+        path_attr_dir: file_dir,
+    };
+
+    let mut declared_modules = Vec::new();
+    collect_declared_modules(
+        &syntax.items,
+        &dirs,
+        &module_path,
+        &gates,
+        &mut declared_modules,
+    );
+
+    files.push(ModuleFile {
+        src_path,
+        module_path,
+        is_root,
+        gates,
+        syntax,
+    });
+
+    for declared_module in declared_modules {
+        collect_module_file(declared_module, false, visited, files)?;
+    }
+    Ok(())
+}
+
+/// Finds the files of all `mod foo;` declarations in `items`, including the ones nested in
+/// inline `mod foo { ... }` blocks.
+fn collect_declared_modules(
+    items: &[syn::Item],
+    dirs: &ModuleDirs,
+    module_path: &[String],
+    gates: &[CfgExpr],
+    declared_modules: &mut Vec<DeclaredModule>,
+) {
+    for item in items {
+        let item_mod = match item {
+            syn::Item::Mod(item_mod) => item_mod,
+            _ => continue,
+        };
+        let name = ident_name(&item_mod.ident);
+        let path_attr = path_attribute(&item_mod.attrs);
+        let mut child_module_path = module_path.to_vec();
+        child_module_path.push(name.clone());
+        let mut child_gates = gates.to_vec();
+        child_gates.extend(CfgExpr::from_cfg_attributes(&item_mod.attrs));
+
+        match item_mod.content {
+            Some((_, ref inline_items)) => {
+                let inline_dir = dirs.module_dir.join(path_attr.unwrap_or(name));
+                let inline_dirs = ModuleDirs {
+                    module_dir: inline_dir.clone(),
+                    path_attr_dir: inline_dir,
+                };
+                collect_declared_modules(
+                    inline_items,
+                    &inline_dirs,
+                    &child_module_path,
+                    &child_gates,
+                    declared_modules,
+                );
+            }
+            None => {
+                let owns_directory = path_attr.is_some();
+                let candidates = match path_attr {
+                    Some(path) => vec![dirs.path_attr_dir.join(path)],
+                    None => vec![
+                        dirs.module_dir.join(format!("{}.rs", name)),
+                        dirs.module_dir.join(&name).join("mod.rs"),
+                    ],
+                };
+                if let Some(src_path) = candidates.into_iter().find(|path| path.is_file()) {
+                    declared_modules.push(DeclaredModule {
+                        src_path,
+                        module_path: child_module_path,
+                        gates: child_gates,
+                        owns_directory,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Value of a `#[path = "..."]` attribute.
+fn path_attribute(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::NameValue(syn::MetaNameValue {
+            ref path,
+            lit: syn::Lit::Str(ref lit),
+            ..
+        })) if path.is_ident("path") => Some(lit.value()),
+        _ => None,
+    })
+}
+
+/// `r#type` -> `type`
+fn ident_name(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    name.trim_start_matches("r#").to_owned()
+}
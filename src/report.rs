@@ -0,0 +1,342 @@
+use std::fmt;
+
+use crate::cfg::*;
+use crate::check::*;
+use crate::check_source::*;
+use crate::lint::*;
+use crate::metadata::*;
+use crate::util::*;
+use crate::{FAILURE, MAYBE, SUCCESS};
+
+/// Results of a `cargo nono check` run, independent of how they are rendered.
+pub struct Report {
+    /// Results of the checked packages. Packages that are shared between workspace members are
+    /// only checked once for each set of active features.
+    pub checks: Vec<CheckResult>,
+    /// The checked workspace members.
+    pub members: Vec<MemberResult>,
+    /// Whether all members of a workspace are checked, instead of a single crate.
+    pub is_workspace: bool,
+}
+
+/// Verdict for a checked workspace member, including its dependencies.
+pub struct MemberResult {
+    pub package_id: String,
+    pub package_name: String,
+    pub tier: SupportTier,
+    /// Most severe level of the findings in the member and its dependencies.
+    pub level: Level,
+}
+
+impl Report {
+    /// Highest tier that is required by any of the checked members.
+    pub fn tier(&self) -> SupportTier {
+        self.members
+            .iter()
+            .map(|member| member.tier)
+            .max()
+            .unwrap_or(SupportTier::Core)
+    }
+
+    /// Most severe level of the findings in all checked members.
+    pub fn max_level(&self) -> Level {
+        self.members
+            .iter()
+            .map(|member| member.level)
+            .max()
+            .unwrap_or(Level::Allow)
+    }
+}
+
+/// Writes the header of a diagnostic for a finding of the lint, which must not be allowed.
+fn write_diagnostic(
+    out: &mut impl fmt::Write,
+    level: Level,
+    lint: Lint,
+    message: fmt::Arguments,
+) -> fmt::Result {
+    let level_str = match level {
+        Level::Deny => "error",
+        _ => "warning",
+    };
+    writeln!(out, "{}[{}]: {}", level_str, lint.code(), message)
+}
+
+/// Writes the offense like a rustc diagnostic, with an optional note that is appended to it.
+fn write_offense(
+    out: &mut impl fmt::Write,
+    check: &CheckResult,
+    offense: &SourceOffense,
+    note: Option<&str>,
+) -> fmt::Result {
+    let level = check.level(offense.lint());
+    if level == Level::Allow {
+        return Ok(());
+    }
+    match offense {
+        SourceOffense::MissingNoStdAttribute => {
+            write_diagnostic(out, level, offense.lint(), format_args!("Did not find a #![no_std] attribute or a simple conditional attribute like #![cfg_attr(not(feature = \"std\"), no_std)] in the crate source. Crate most likely doesn't support no_std without changes."))?;
+            if let Some(src_path) = &check.src_path {
+                // crate-level findings point to the start of the crate root, like in rustc
+                writeln!(
+                    out,
+                    "   --> {}:1:1",
+                    relative_to_current_dir(src_path).display()
+                )?;
+            }
+        }
+        SourceOffense::UseStdStatement(stmt) => {
+            write_diagnostic(
+                out,
+                level,
+                offense.lint(),
+                format_args!(
+                    "Source code contains an explicit `use std::` statement in `{}`.",
+                    stmt.item_path()
+                ),
+            )?;
+            write!(out, "{}", stmt)?;
+        }
+        SourceOffense::StdPath(usage) => {
+            write_diagnostic(
+                out,
+                level,
+                offense.lint(),
+                format_args!(
+                    "Source code contains a fully qualified `std::` path in `{}`.",
+                    usage.item_path()
+                ),
+            )?;
+            write!(out, "{}", usage)?;
+        }
+        SourceOffense::StdMacro(usage) => {
+            write_diagnostic(
+                out,
+                level,
+                offense.lint(),
+                format_args!(
+                    "Source code invokes the std-only macro `{}` in `{}`.",
+                    usage.path(),
+                    usage.item_path()
+                ),
+            )?;
+            write!(out, "{}", usage)?;
+        }
+    }
+    if let Some(note) = note {
+        writeln!(out, "  = note: {}", note)?;
+    }
+    writeln!(out)
+}
+
+fn write_check(
+    out: &mut impl fmt::Write,
+    check: &CheckResult,
+    metadata: &CargoMetadata,
+) -> fmt::Result {
+    let overall_res = match check.max_level() {
+        Level::Deny => FAILURE,
+        Level::Warn => MAYBE,
+        Level::Allow => SUCCESS,
+    };
+    writeln!(
+        out,
+        "{}: {} ({})",
+        check.package_name,
+        overall_res,
+        check.tier()
+    )?;
+    if check.no_std_itself() {
+        for usage in check.active_alloc_usages() {
+            writeln!(
+                out,
+                "note: Requires `alloc` because of `{}` in `{}`.",
+                usage.path(),
+                usage.item_path()
+            )?;
+            if let Some(replacement) = usage.macro_replacement() {
+                writeln!(
+                    out,
+                    "  = help: Try replacing `{}` with {}.",
+                    usage.path(),
+                    replacement
+                )?;
+            }
+        }
+        return Ok(());
+    }
+    if check.max_level() == Level::Allow {
+        return Ok(());
+    }
+    if !check.is_main_pkg {
+        writeln!(
+            out,
+            "note: Dependency path: {}",
+            check.dependency_path.join(" -> ")
+        )?;
+        if !check.affected_members.is_empty() {
+            writeln!(
+                out,
+                "note: Affects workspace members: {}",
+                check.affected_members.join(", ")
+            )?;
+        }
+    }
+    if let CrateSupport::Unchecked(error) = &check.support {
+        writeln!(
+            out,
+            "warning: Could not check the source of the crate: {}",
+            error.message
+        )?;
+        writeln!(out, "   --> {}", error.location())?;
+        writeln!(out)?;
+    }
+    if let CrateSupport::ConditionalNoStd { condition, .. } = &check.support {
+        if !condition.eval(&check.active_cfg()) {
+            match check.features_to_disable() {
+                Some(features) => {
+                    let level = check.level(Lint::StdFeature);
+                    for feature in features.iter().filter(|_| level != Level::Allow) {
+                        write_diagnostic(
+                            out,
+                            level,
+                            Lint::StdFeature,
+                            format_args!(
+                                "Crate supports no_std if \"{}\" feature is deactivated.",
+                                feature
+                            ),
+                        )?;
+                        let feat = check.find_active_feature_by_name(feature).unwrap();
+                        feat.write(out, metadata, 1)?;
+                        if check.resolver1_only_features.contains(feature) {
+                            writeln!(out, "  = note: The \"{}\" feature is only activated via a build dependency, dev dependency or dependency for another target, but resolver 1 unifies it with the features of the normal dependencies.", feature)?;
+                            writeln!(out, "  = help: Try setting `resolver = \"2\"` in the `[workspace]` or `[package]` section of the workspace Cargo.toml.")?;
+                        }
+                        writeln!(out)?;
+                    }
+                }
+                None => {
+                    let level = check.level(Lint::UnsatisfiableNoStdCondition);
+                    if level != Level::Allow {
+                        write_diagnostic(out, level, Lint::UnsatisfiableNoStdCondition, format_args!(
+                            "Crate only supports no_std if `{}` holds, which can not be achieved by deactivating features.",
+                            condition
+                        ))?;
+                        if let Some(src_path) = &check.src_path {
+                            writeln!(
+                                out,
+                                "   --> {}:1:1",
+                                relative_to_current_dir(src_path).display()
+                            )?;
+                        }
+                        writeln!(out)?;
+                    }
+                }
+            }
+        }
+        for offense in check.leaked_offenses() {
+            write_offense(out, check, offense, Some("Crate uses std in code that is not gated behind the features that have to be deactivated for no_std."))?;
+        }
+    }
+    for offense in check.active_offenses() {
+        write_offense(out, check, offense, None)?;
+    }
+    for offense in check.compiled_out_offenses() {
+        if let Some(usage) = offense.std_usage() {
+            let gate = match offense.gates() {
+                [gate] => gate.clone(),
+                gates => CfgExpr::All(gates.to_vec()),
+            };
+            writeln!(
+                out,
+                "note: Ignoring usage of `{}` in `{}`, as it is compiled out by `cfg({})`.",
+                usage.path(),
+                usage.item_path(),
+                gate
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Human readable result of a single checked package, including its offenses and the causes of
+/// the features that make it require std.
+pub fn check_to_string(check: &CheckResult, metadata: &CargoMetadata) -> String {
+    let mut out = String::new();
+    write_check(&mut out, check, metadata).unwrap();
+    out
+}
+
+/// Prints the report in the human readable format.
+pub fn print_report(report: &Report, metadata: &CargoMetadata) {
+    for check in &report.checks {
+        print!("{}", check_to_string(check, metadata));
+    }
+
+    // like rustc, point to the long-form explanations of the reported errors
+    let mut codes: Vec<_> = report
+        .checks
+        .iter()
+        .flat_map(|check| {
+            check
+                .lints()
+                .into_iter()
+                .filter(move |lint| check.level(*lint) != Level::Allow)
+        })
+        .map(|lint| lint.code())
+        .collect();
+    codes.sort();
+    codes.dedup();
+    match codes.as_slice() {
+        [] => {}
+        [code] => {
+            println!(
+                "For more information about this error, try `cargo nono explain {}`.",
+                code
+            );
+        }
+        [code, ..] => {
+            println!(
+                "Some errors have detailed explanations: {}.",
+                codes.join(", ")
+            );
+            println!(
+                "For more information about an error, try `cargo nono explain {}`.",
+                code
+            );
+        }
+    }
+
+    let tier = report.tier();
+    println!();
+    if report.is_workspace {
+        println!("Workspace members:");
+        for member in &report.members {
+            let overall_res = match member.level {
+                Level::Deny => FAILURE,
+                Level::Warn => MAYBE,
+                Level::Allow => SUCCESS,
+            };
+            println!("{}: {} ({})", member.package_name, overall_res, member.tier);
+        }
+        println!();
+        match tier {
+            SupportTier::Core => println!("Workspace members and their dependencies only require `core`."),
+            SupportTier::Alloc => println!("Workspace members and their dependencies require `alloc`, so a global allocator has to be provided."),
+            SupportTier::Std => println!(
+                "{} of {} workspace members require `std`.",
+                report.members.iter().filter(|n| n.tier == SupportTier::Std).count(),
+                report.members.len()
+            ),
+        }
+    } else {
+        match tier {
+            SupportTier::Core => println!("Crate and its dependencies only require `core`."),
+            SupportTier::Alloc => println!("Crate and its dependencies require `alloc`, so a global allocator has to be provided."),
+            SupportTier::Std => println!("Crate and its dependencies require `std`."),
+        }
+    }
+    if tier == SupportTier::Std && report.max_level() != Level::Deny {
+        println!("All findings that require `std` are allowed or only warned about.");
+    }
+}
@@ -0,0 +1,373 @@
+use std::fs;
+use std::path::Path;
+
+use cargo_metadata::{Dependency, DependencyKind};
+use serde_json::{json, Value};
+
+use crate::check::*;
+use crate::check_source::*;
+use crate::ext::*;
+use crate::lint::*;
+use crate::metadata::*;
+use crate::report::*;
+use crate::util::*;
+
+/// Location of a file, relative to the current directory if it is inside of it.
+fn artifact_location(path: &Path) -> Value {
+    let path = relative_to_current_dir(path);
+    match path.is_absolute() {
+        true => json!({ "uri": format!("file://{}", path.display()) }),
+        false => json!({ "uri": path.display().to_string() }),
+    }
+}
+
+fn physical_location(path: &Path, start_line: Option<usize>, start_column: Option<usize>) -> Value {
+    let mut location = json!({ "artifactLocation": artifact_location(path) });
+    if let Some(start_line) = start_line {
+        location["region"] = json!({ "startLine": start_line });
+        if let Some(start_column) = start_column {
+            location["region"]["startColumn"] = json!(start_column);
+        }
+    }
+    location
+}
+
+/// Result for a finding of the lint, or `None` if the lint is allowed for the crate. The name of
+/// the lint is used as rule id, as it is stable, so that code-scanning dashboards can track
+/// results across runs.
+fn result(
+    check: &CheckResult,
+    lint: Lint,
+    message: String,
+    physical_location: Value,
+) -> Option<Value> {
+    let level = match check.level(lint) {
+        Level::Allow => return None,
+        Level::Warn => "warning",
+        Level::Deny => "error",
+    };
+    Some(json!({
+        "ruleId": lint.name(),
+        "ruleIndex": Lint::ALL.iter().position(|n| *n == lint).unwrap(),
+        "level": level,
+        "message": { "text": message },
+        "locations": [{ "physicalLocation": physical_location }],
+    }))
+}
+
+/// Fix that replaces the leading `std` of the path of the usage with `core`. The paths of a
+/// grouped use tree like `use std::{fmt, ops::Add};` share their `std`, so it is only replaced if
+/// all of them are available in `core`.
+fn std_fix(usage: &StdUsage, offenses: &[&SourceOffense]) -> Option<Value> {
+    usage.std_replacement()?;
+    let root_location = usage.root_location();
+    let all_replaceable = offenses
+        .iter()
+        .filter_map(|offense| offense.std_usage())
+        .filter(|other| {
+            other.relative_src_path() == usage.relative_src_path()
+                && other.root_location() == root_location
+        })
+        .all(|other| other.std_replacement().is_some());
+    if !all_replaceable {
+        return None;
+    }
+    let (line, start_column, end_column) = root_location;
+    Some(json!([{
+        "description": { "text": "Replace `std` with `core`." },
+        "artifactChanges": [{
+            "artifactLocation": artifact_location(usage.relative_src_path()),
+            "replacements": [{
+                "deletedRegion": {
+                    "startLine": line,
+                    "startColumn": start_column,
+                    "endLine": line,
+                    "endColumn": end_column,
+                },
+                "insertedContent": { "text": "core" },
+            }],
+        }],
+    }]))
+}
+
+/// Result for the offense, which is one of the reported `offenses` of the crate.
+fn offense_result(
+    check: &CheckResult,
+    offense: &SourceOffense,
+    offenses: &[&SourceOffense],
+) -> Option<Value> {
+    let usage = match offense.std_usage() {
+        Some(usage) => usage,
+        None => {
+            let src_path = check.src_path.as_ref()?;
+            let message = format!(
+                "Crate `{}:{}` doesn't have a #![no_std] attribute.",
+                check.package_name, check.package_version
+            );
+            return result(
+                check,
+                offense.lint(),
+                message,
+                physical_location(src_path, None, None),
+            );
+        }
+    };
+    let message = match offense {
+        SourceOffense::UseStdStatement(_) => format!(
+            "Source code contains an explicit `use std::` statement in `{}`.",
+            usage.item_path()
+        ),
+        SourceOffense::StdMacro(_) => format!(
+            "Source code invokes the std-only macro `{}` in `{}`.",
+            usage.path(),
+            usage.item_path()
+        ),
+        _ => format!(
+            "Source code contains a fully qualified `std::` path in `{}`.",
+            usage.item_path()
+        ),
+    };
+    let message = match usage.macro_replacement() {
+        Some(replacement) => format!(
+            "{} Try replacing `{}` with {}.",
+            message,
+            usage.path(),
+            replacement
+        ),
+        None => message,
+    };
+
+    let (start_line, start_column) = usage.line_column();
+    let mut result = result(
+        check,
+        offense.lint(),
+        message,
+        physical_location(
+            usage.relative_src_path(),
+            Some(start_line),
+            Some(start_column),
+        ),
+    )?;
+    if let Some(fixes) = std_fix(usage, offenses) {
+        result["fixes"] = fixes;
+    }
+    Some(result)
+}
+
+/// Table of `Cargo.toml` in which the dependency is declared, e.g. `dev-dependencies` or
+/// `target.cfg(unix).dependencies`.
+fn dependency_table(dependency: &Dependency) -> String {
+    let table = match dependency.kind {
+        DependencyKind::Development => "dev-dependencies",
+        DependencyKind::Build => "build-dependencies",
+        _ => "dependencies",
+    };
+    match &dependency.target {
+        Some(platform) => format!("target.{}.{}", platform, table),
+        None => table.to_owned(),
+    }
+}
+
+/// Whether the header of a `Cargo.toml` table names `table`. Quotes and whitespace are ignored,
+/// so that `[target.'cfg(unix)'.dependencies]` names `target.cfg(unix).dependencies`.
+fn is_manifest_table(header: &str, table: &str) -> bool {
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| !c.is_whitespace() && *c != '\'' && *c != '"')
+            .collect::<String>()
+    };
+    normalize(header) == normalize(table)
+}
+
+/// Line of `Cargo.toml` on which the key is declared in the table, e.g. `serde` in
+/// `[dependencies]` or `[dependencies.serde]`.
+fn find_manifest_line(manifest_path: &Path, table: &str, key: &str) -> Option<usize> {
+    let manifest = fs::read_to_string(manifest_path).ok()?;
+    let mut in_table = false;
+    for (index, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            // `[dependencies.serde]`
+            if is_manifest_table(header, &format!("{}.{}", table, key)) {
+                return Some(index + 1);
+            }
+            in_table = is_manifest_table(header, table);
+            continue;
+        }
+        let line_key = line.split(['=', '.']).next().unwrap().trim();
+        if in_table && line_key.trim_matches('"') == key {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// Location in a `Cargo.toml` where the feature is enabled, i.e. where the origin of its chain of
+/// causes is declared.
+fn feature_location(feature: &Feature, metadata: &CargoMetadata) -> Option<Value> {
+    let mut origin = feature;
+    while let Some(FeatureCause::Feature(cause_feature)) = origin.causes.first() {
+        origin = cause_feature;
+    }
+    let origin_package = metadata.find_package(&origin.package_id)?;
+    match origin.causes.first() {
+        Some(FeatureCause::Default(package_id)) | Some(FeatureCause::Explicit(package_id))
+            if package_id != &origin.package_id =>
+        {
+            let dependent = metadata.find_package(package_id)?;
+            let is_default = matches!(origin.causes.first(), Some(FeatureCause::Default(_)));
+            // a dependency can be declared several times, e.g. for a specific platform or as dev
+            // dependency, so prefer the normal declaration that requests the feature
+            let dependency_line = dependent
+                .dependencies
+                .iter()
+                .filter(|dependency| {
+                    metadata
+                        .dependency_package_id(dependent, dependency)
+                        .as_ref()
+                        == Some(&origin.package_id)
+                })
+                .min_by_key(|dependency| {
+                    let requests_feature = match is_default {
+                        true => dependency.uses_default_features,
+                        false => dependency.features.contains(&origin.name),
+                    };
+                    (dependency.kind != DependencyKind::Normal, !requests_feature)
+                })
+                .and_then(|dependency| {
+                    find_manifest_line(
+                        &dependent.manifest_path,
+                        &dependency_table(dependency),
+                        dependency.local_name(),
+                    )
+                });
+            Some(physical_location(
+                &dependent.manifest_path,
+                dependency_line,
+                None,
+            ))
+        }
+        // enabled by default or via the `--features` flag for the checked crate itself
+        _ => Some(physical_location(
+            &origin_package.manifest_path,
+            find_manifest_line(&origin_package.manifest_path, "features", &origin.name),
+            None,
+        )),
+    }
+}
+
+fn check_results(check: &CheckResult, metadata: &CargoMetadata) -> Vec<Value> {
+    let mut results = vec![];
+    if check.no_std_itself() {
+        return results;
+    }
+    if let CrateSupport::ConditionalNoStd { condition, .. } = &check.support {
+        if !condition.eval(&check.active_cfg()) {
+            match check.features_to_disable() {
+                Some(features) => {
+                    for feature in features {
+                        let active_feature = check.find_active_feature_by_name(&feature).unwrap();
+                        let message = format!(
+                            "The \"{}\" feature of `{}:{}` is enabled, but the crate only supports no_std if it is deactivated. Dependency path: {}",
+                            feature,
+                            check.package_name,
+                            check.package_version,
+                            check.dependency_path.join(" -> ")
+                        );
+                        if let Some(location) = feature_location(active_feature, metadata) {
+                            results.extend(result(check, Lint::StdFeature, message, location));
+                        }
+                    }
+                }
+                None => {
+                    if let Some(src_path) = &check.src_path {
+                        let message = format!(
+                            "Crate `{}:{}` only supports no_std if `{}` holds, which can not be achieved by deactivating features.",
+                            check.package_name, check.package_version, condition
+                        );
+                        results.extend(result(
+                            check,
+                            Lint::UnsatisfiableNoStdCondition,
+                            message,
+                            physical_location(src_path, None, None),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    let offenses: Vec<_> = check
+        .leaked_offenses()
+        .into_iter()
+        .chain(check.active_offenses())
+        .collect();
+    for offense in &offenses {
+        results.extend(offense_result(check, offense, &offenses));
+    }
+    results
+}
+
+/// Renders the report for `--message-format sarif`, as a SARIF 2.1.0 log with a single run.
+pub fn report_to_sarif(report: &Report, metadata: &CargoMetadata) -> String {
+    let rules: Vec<_> = Lint::ALL
+        .iter()
+        .map(|lint| {
+            json!({
+                "id": lint.name(),
+                "shortDescription": { "text": lint.description() },
+                "help": { "text": lint.explanation(), "markdown": lint.explanation() },
+                "properties": { "code": lint.code() },
+            })
+        })
+        .collect();
+    let results: Vec<_> = report
+        .checks
+        .iter()
+        .flat_map(|check| check_results(check, metadata))
+        .collect();
+    // crates that can not be checked are not findings of a rule
+    let notifications: Vec<_> = report
+        .checks
+        .iter()
+        .filter_map(|check| match &check.support {
+            CrateSupport::Unchecked(error) => Some(json!({
+                "level": "warning",
+                "message": {
+                    "text": format!(
+                        "Could not check the source of `{}:{}`: {}",
+                        check.package_name, check.package_version, error.message
+                    )
+                },
+                "locations": [{
+                    "physicalLocation": physical_location(
+                        &error.src_path,
+                        error.line_column.map(|(line, _)| line),
+                        error.line_column.map(|(_, column)| column),
+                    )
+                }],
+            })),
+            _ => None,
+        })
+        .collect();
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cargo-nono",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/hobofan/cargo-nono",
+                    "rules": rules,
+                }
+            },
+            "invocations": [{
+                "executionSuccessful": true,
+                "toolExecutionNotifications": notifications,
+            }],
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&sarif).unwrap()
+}
@@ -1,26 +1,72 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str::from_utf8;
-use cargo_metadata::{Dependency, Metadata, Package, PackageId};
+use cargo_metadata::{Metadata, Package, PackageId};
+use serde::Deserialize;
 
+use crate::cfg::Cfg;
 use crate::ext::{Feature, FeatureCause};
+use crate::lint::{Level, Lint, LintLevels};
+use crate::metadata::CargoMetadata;
 
-pub fn metadata_run(additional_args: Option<String>) -> Result<Metadata, ()> {
+/// Runs `cargo metadata`, resolved with the provided arguments like `--features`.
+pub fn metadata_run(additional_args: &[String]) -> Result<CargoMetadata, ()> {
     let cargo = env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
     let mut cmd = Command::new(cargo);
     cmd.arg("metadata");
-    cmd.args(&["--format-version", "1"]);
-    if let Some(additional_args) = additional_args {
-        cmd.arg(&additional_args);
-    }
+    cmd.args(["--format-version", "1"]);
+    cmd.args(additional_args);
 
     let output = cmd.output().unwrap();
+    if !output.status.success() {
+        eprint!("{}", from_utf8(&output.stderr).unwrap());
+        std::process::exit(1);
+    }
     let stdout = from_utf8(&output.stdout).unwrap();
-    let meta = serde_json::from_str(stdout)
+    let meta = CargoMetadata::from_json(stdout)
         .expect("Fetching metadata failed. Please call cargo-nono from within a cargo project.");
     Ok(meta)
 }
 
+/// Arguments for `cargo metadata`, so that it resolves the features and platform that are checked.
+pub fn metadata_args_from_args(
+    no_default: bool,
+    features_args: &[String],
+    package_arg: Option<&str>,
+    manifest_path_arg: Option<&str>,
+    target_cfg: &Cfg,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(manifest_path) = manifest_path_arg {
+        args.push("--manifest-path".to_owned());
+        args.push(manifest_path.to_owned());
+    }
+    if no_default {
+        args.push("--no-default-features".to_owned());
+    }
+    let features: Vec<_> = features_args
+        .iter()
+        .flat_map(|features_args_str| features_args_str.split(','))
+        .filter(|feat| !feat.is_empty())
+        .map(|feat| match package_arg {
+            // features of other workspace members than the current one have to be qualified
+            Some(package_name) if !feat.contains('/') => format!("{}/{}", package_name, feat),
+            _ => feat.to_owned(),
+        })
+        .collect();
+    if !features.is_empty() {
+        args.push("--features".to_owned());
+        args.push(features.join(","));
+    }
+    if let Some(triple) = target_cfg.triple() {
+        args.push("--filter-platform".to_owned());
+        args.push(triple.to_owned());
+    }
+    args
+}
+
 pub fn features_from_args(
     package_id: String,
     no_default: bool,
@@ -46,13 +92,77 @@ pub fn features_from_args(
     features
 }
 
+/// All workspace members, except the ones excluded via `--exclude`.
+pub fn workspace_members_from_args<'a>(
+    metadata: &'a Metadata,
+    excludes: &[&str],
+) -> Vec<&'a Package> {
+    let workspace_packages: Vec<_> = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .collect();
+    for exclude in excludes {
+        if !workspace_packages.iter().any(|p| p.name == *exclude) {
+            let package_names: Vec<_> = workspace_packages.iter().map(|n| n.name.clone()).collect();
+            println!(
+                "⚠️  Unknown package \"{}\". Please provide one of {:?} via --exclude flag.",
+                exclude, package_names
+            );
+            std::process::exit(1);
+        }
+    }
+    workspace_packages
+        .into_iter()
+        .filter(|p| !excludes.contains(&p.name.as_str()))
+        .collect()
+}
+
+/// Directory of the manifest that is passed via `--manifest-path`, or the current directory.
+pub fn manifest_dir_from_args(manifest_path_arg: Option<&str>) -> PathBuf {
+    match manifest_path_arg {
+        // cargo metadata already failed if the manifest doesn't exist
+        Some(manifest_path) => Path::new(manifest_path)
+            .canonicalize()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf(),
+        None => env::current_dir().unwrap(),
+    }
+}
+
+/// Whether `manifest_dir` contains a virtual manifest, i.e. the root of a workspace without a
+/// root package.
+pub fn is_virtual_workspace_root(metadata: &Metadata, manifest_dir: &Path) -> bool {
+    same_dir(&metadata.workspace_root, manifest_dir)
+        && !metadata.packages.iter().any(|p| {
+            p.manifest_path
+                .parent()
+                .is_some_and(|n| same_dir(n, manifest_dir))
+        })
+}
+
+/// Path relative to the current directory if it is inside of it, e.g. for printing source paths.
+pub fn relative_to_current_dir(path: &Path) -> &Path {
+    let current_dir = env::current_dir().unwrap();
+    path.strip_prefix(current_dir).unwrap_or(path)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 pub fn main_ws_member_from_args<'a>(
     metadata: &'a Metadata,
     package_arg: Option<&str>,
+    manifest_dir: &Path,
 ) -> &'a PackageId {
-    let target_workspace_member;
     if metadata.workspace_members.len() == 1 {
-        target_workspace_member = metadata.workspace_members.get(0).unwrap();
+        metadata.workspace_members.first().unwrap()
     } else {
         let workspace_members = &metadata.workspace_members[..];
         let workspace_packages: Vec<_> = metadata
@@ -65,7 +175,7 @@ pub fn main_ws_member_from_args<'a>(
             .map(|n| n.name.clone())
             .collect();
 
-        target_workspace_member = match package_arg {
+        match package_arg {
             Some(package_name) => {
                 let member = workspace_packages
                     .iter()
@@ -80,12 +190,11 @@ pub fn main_ws_member_from_args<'a>(
                 &member.unwrap().id
             }
             None => {
-                let current_dir = env::current_dir().unwrap();
                 let member = workspace_packages
                     .iter()
                     .find(|p| {
                         if let Some(package_dir) = p.manifest_path.parent() {
-                            package_dir == current_dir.as_path()
+                            same_dir(package_dir, manifest_dir)
                         } else {
                             false
                         }
@@ -99,42 +208,84 @@ pub fn main_ws_member_from_args<'a>(
                     }
                 &member.unwrap().id
             }
-        };
+        }
     }
-    target_workspace_member
 }
 
-pub fn dependencies_to_packages(
-    package: &Package,
-    metadata: &Metadata,
-    dependencies: &[Dependency],
-) -> Vec<Package> {
-    let resolve_node = metadata
-        .resolve
-        .clone()
-        .unwrap()
-        .nodes
-        .into_iter()
-        .find(|n| n.id == package.id)
-        .unwrap();
-    // All dependency packages of the package
-    let dependency_packages: Vec<Package> = metadata
+/// `[workspace.metadata.nono]` or `[package.metadata.nono]` table of the root manifest.
+#[derive(Deserialize, Default)]
+struct NonoConfig {
+    /// lint name -> level
+    #[serde(default)]
+    lints: HashMap<String, Level>,
+    /// crate name -> lint name -> level
+    #[serde(default)]
+    crates: HashMap<String, HashMap<String, Level>>,
+}
+
+fn find_lint(name: &str, source: &str) -> Lint {
+    match Lint::find(name) {
+        Some(lint) => lint,
+        None => {
+            let lint_names: Vec<_> = Lint::ALL.iter().map(|n| n.name()).collect();
+            println!(
+                "⚠️  Unknown lint \"{}\". Please provide one of {:?} {}.",
+                name, lint_names, source
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Lint levels from the `[workspace.metadata.nono]` and `[package.metadata.nono]` tables of the
+/// root manifest, with the `-A`, `-W` and `-D` flags (`level_args`, in the order they were
+/// passed) overriding the levels for all crates.
+///
+/// Levels that are configured for a single crate take precedence over both.
+pub fn lint_levels_from_args(metadata: &CargoMetadata, level_args: &[(Level, &str)]) -> LintLevels {
+    let root_manifest_path = metadata.workspace_root.join("Cargo.toml");
+    let root_package_metadata = metadata
         .packages
         .iter()
-        .filter(|n| resolve_node.dependencies.contains(&n.id))
-        .map(|n| n.clone())
-        .collect();
+        .find(|package| package.manifest_path == root_manifest_path)
+        .map(|package| &package.metadata);
+    let configs = vec![
+        (
+            "[workspace.metadata.nono]",
+            Some(metadata.workspace_metadata()),
+        ),
+        ("[package.metadata.nono]", root_package_metadata),
+    ];
 
-    // limit packages to only the activated dependencies
-    dependency_packages
-        .into_iter()
-        .filter(|package| {
-            for dependency in dependencies.iter() {
-                if package.name == dependency.name {
-                    return true;
-                }
+    let mut lint_levels = LintLevels::default();
+    let mut crate_levels = vec![];
+    for (table, package_metadata) in configs {
+        let config = match package_metadata.and_then(|n| n.get("nono")) {
+            Some(config) => config,
+            None => continue,
+        };
+        let config: NonoConfig = match serde_json::from_value(config.clone()) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("⚠️  Invalid {} table in Cargo.toml: {}", table, err);
+                std::process::exit(1);
             }
-            return false;
-        })
-        .collect()
+        };
+        let source = format!("in the {} table", table);
+        for (name, level) in config.lints {
+            lint_levels.set(find_lint(&name, &source), level);
+        }
+        for (crate_name, levels) in config.crates {
+            for (name, level) in levels {
+                crate_levels.push((crate_name.clone(), find_lint(&name, &source), level));
+            }
+        }
+    }
+    for (level, name) in level_args {
+        lint_levels.set(find_lint(name, "via -A, -W or -D flag"), *level);
+    }
+    for (crate_name, lint, level) in crate_levels {
+        lint_levels.set_for_crate(&crate_name, lint, level);
+    }
+    lint_levels
 }
@@ -1,5 +1,3 @@
 #![no_std]
 
-fn main() {
-    println!("Hello, world!");
-}
+fn main() {}
@@ -1,5 +1,3 @@
 #![cfg_attr(not(test), no_std)]
 
-fn main() {
-    println!("Hello, world!");
-}
+fn main() {}
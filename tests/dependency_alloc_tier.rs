@@ -0,0 +1,49 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_alloc_tier")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+fn package_line<'a>(output: &'a str, package_name: &str) -> &'a str {
+    output
+        .lines()
+        .find(|line| line.starts_with(&format!("{}:", package_name)))
+        .unwrap()
+}
+
+#[test]
+fn it_succeeds() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_alloc_tier")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_reports_tier_per_crate() {
+    let output = check_output();
+    assert!(package_line(&output, "core_only").ends_with("(core)"));
+    assert!(package_line(&output, "needs_alloc").ends_with("(alloc)"));
+    assert!(output.contains("Requires `alloc` because of `alloc::vec::Vec` in `crate::prelude`"));
+    // only imported via a glob from `crate::prelude`
+    assert!(output.contains("Requires `alloc` because of `String` in `crate::names`"));
+    assert!(output.contains("Try replacing `vec!` with `alloc::vec!`"));
+}
+
+#[test]
+fn it_rolls_up_to_highest_tier() {
+    let output = check_output();
+    assert!(output.contains("Crate and its dependencies require `alloc`"));
+}
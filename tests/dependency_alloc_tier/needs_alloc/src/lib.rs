@@ -0,0 +1,18 @@
+#![no_std]
+
+#[macro_use]
+extern crate alloc;
+
+mod prelude;
+
+use crate::prelude::*;
+
+pub fn names() -> Vec<String> {
+    let mut names = Vec::new();
+    names.push(String::from("core"));
+    names
+}
+
+pub fn versions() -> Vec<u8> {
+    vec![1, 2]
+}
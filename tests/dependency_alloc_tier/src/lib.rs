@@ -0,0 +1,4 @@
+#![no_std]
+
+pub use core_only::checksum;
+pub use needs_alloc::names;
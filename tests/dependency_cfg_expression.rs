@@ -0,0 +1,40 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_cfg_expression")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_succeeds_with_no_default_features() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--no-default-features")
+        .current_dir("./tests/dependency_cfg_expression")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_prints_all_features_that_need_to_be_deactivated() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_cfg_expression")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("Crate supports no_std if \"std\" feature is deactivated."));
+    assert!(output.contains("Crate supports no_std if \"io\" feature is deactivated."));
+}
@@ -0,0 +1 @@
+#![cfg_attr(not(any(feature = "std", feature = "io", test)), no_std)]
@@ -39,3 +39,19 @@ fn it_prints_cause() {
         "Caused by implicitly enabled default feature from \"dependency_default_std:0.1.0\"";
     assert!(output.contains(expected_cause));
 }
+
+#[test]
+fn it_fails_for_unknown_feature() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--features")
+        .arg("nonexistent")
+        .current_dir("./tests/dependency_default_std")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("does not have the feature `nonexistent`"));
+}
@@ -0,0 +1,69 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(args: &[&str]) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/dependency_feature_syntax")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_does_not_activate_dependency_via_weak_feature() {
+    let output = check_output(&[]);
+    assert!(output.contains("Caused by feature flag \"helper/std\""));
+    assert!(!output.contains("serde_like"));
+}
+
+#[test]
+fn it_activates_weak_feature_of_enabled_dependency() {
+    let output = check_output(&["--features", "serde"]);
+    assert!(output.contains("Caused by feature flag \"serde_like?/std\""));
+}
+
+#[test]
+fn it_activates_dependency_via_dep_prefix() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--no-default-features")
+        .arg("--features")
+        .arg("serde")
+        .current_dir("./tests/dependency_feature_syntax")
+        .assert()
+        .success();
+    let output = check_output(&["--no-default-features", "--features", "serde"]);
+    assert!(output.contains("serde_like"));
+}
+
+#[test]
+fn it_activates_dependency_feature_via_cli_flag() {
+    let output = check_output(&["--no-default-features", "--features", "helper/std"]);
+    assert!(output.contains("helper: FAILURE"));
+    assert!(output.contains("Caused by providing CLI --features flag \"helper/std\""));
+}
+
+#[test]
+fn it_activates_implicit_feature_via_dependency_feature() {
+    let output = check_output(&["--no-default-features", "--features", "extra"]);
+    assert!(output.contains("opt_like: FAILURE"));
+    assert!(output.contains("Caused by feature flag \"opt_like/std\""));
+
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--verify-model")
+        .arg("--no-default-features")
+        .arg("--features")
+        .arg("extra")
+        .current_dir("./tests/dependency_feature_syntax")
+        .assert()
+        .success();
+}
@@ -0,0 +1,30 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_succeeds() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_multiple_versions")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_checks_the_selected_version() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_multiple_versions")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    // `versioned:0.1.0` is only a dev dependency, that is renamed to `versioned_v1`
+    assert!(!output.contains("versioned_v1"));
+    assert!(!output.contains("FAILURE"));
+}
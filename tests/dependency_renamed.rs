@@ -0,0 +1,40 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_fails_with_default_features() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_renamed")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_resolves_features_of_renamed_dependency() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_renamed")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("Crate supports no_std if \"std\" feature is deactivated."));
+    assert!(output.contains("Caused by feature flag \"leaf_crate/std\""));
+}
+
+#[test]
+fn it_succeeds_without_default_features() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--no-default-features")
+        .current_dir("./tests/dependency_renamed")
+        .assert()
+        .success();
+}
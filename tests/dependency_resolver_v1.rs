@@ -0,0 +1,29 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_fails_because_of_build_dependency_features() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_resolver_v1")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_suggests_resolver_2() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_resolver_v1")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("The \"std\" feature is only activated via a build dependency, dev dependency or dependency for another target"));
+    assert!(output.contains("Try setting `resolver = \"2\"`"));
+}
@@ -0,0 +1,14 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_succeeds_as_build_dependency_features_are_not_unified() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_resolver_v2")
+        .assert()
+        .success();
+}
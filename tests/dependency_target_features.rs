@@ -0,0 +1,31 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(args: &[&str]) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/dependency_target_features")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+#[cfg(unix)]
+fn it_activates_features_of_platform_specific_declarations() {
+    let output = check_output(&[]);
+    assert!(output.contains("leaf: FAILURE"));
+    assert!(output.contains("feature from \"dependency_target_features:0.1.0\""));
+}
+
+#[test]
+#[cfg(unix)]
+fn it_explains_features_of_platform_specific_declarations() {
+    let output = check_output(&["--verify-model"]);
+    assert!(output.contains("Feature model matches Cargo's resolve for all 2 crates."));
+}
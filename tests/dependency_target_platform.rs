@@ -0,0 +1,59 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(args: &[&str]) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/dependency_target_platform")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn it_checks_dependencies_of_host_platform() {
+    let output = check_output(&[]);
+
+    assert!(output.contains("unix_only: FAILURE"));
+    assert!(output.contains("linux_only: FAILURE"));
+    assert!(!output.contains("bare_metal_only"));
+}
+
+#[test]
+fn it_skips_dependencies_of_other_platforms() {
+    let output = check_output(&["--target", "thumbv7em-none-eabihf"]);
+
+    assert!(output.contains("bare_metal_only: SUCCESS"));
+    assert!(!output.contains("unix_only"));
+    assert!(!output.contains("linux_only"));
+}
+
+#[test]
+fn it_succeeds_for_bare_metal_target() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--target")
+        .arg("thumbv7em-none-eabihf")
+        .current_dir("./tests/dependency_target_platform")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_fails_for_unknown_target() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--target")
+        .arg("not-a-target")
+        .current_dir("./tests/dependency_target_platform")
+        .assert()
+        .code(1);
+}
@@ -0,0 +1,46 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_transitive")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_transitive")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_checks_transitive_dependencies() {
+    let output = check_output();
+    assert!(output.contains("Crate supports no_std if \"std\" feature is deactivated."));
+    assert!(output
+        .contains("Dependency path: dependency_transitive:0.1.0 -> middle:0.1.0 -> leaf:0.1.0"));
+}
+
+#[test]
+fn it_propagates_features_of_dependencies() {
+    let output = check_output();
+    assert!(output.contains("Caused by feature flag \"leaf/std\" in crate \"middle:0.1.0\""));
+}
+
+#[test]
+fn it_does_not_walk_into_proc_macros() {
+    let output = check_output();
+    assert!(output.contains("derive_helper"));
+    assert!(!output.contains("std_only"));
+}
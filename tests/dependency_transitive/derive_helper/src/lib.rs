@@ -0,0 +1 @@
+extern crate proc_macro;
@@ -0,0 +1 @@
+#![no_std]
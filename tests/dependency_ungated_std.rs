@@ -0,0 +1,39 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_ungated_std")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_ungated_std")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_reports_ungated_std_usage() {
+    let output = check_output();
+    assert!(output.contains("Crate uses std in code that is not gated"));
+    assert!(output.contains("leaky/src/collections.rs"));
+}
+
+#[test]
+fn it_ignores_gated_std_usage() {
+    let output = check_output();
+    assert!(!output.contains("leaky/src/io_impl.rs"));
+    assert!(!output.contains("leaky/src/lib.rs"));
+}
@@ -0,0 +1 @@
+use std::collections::HashMap;
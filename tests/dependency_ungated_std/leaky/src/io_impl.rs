@@ -0,0 +1 @@
+use std::io::Read;
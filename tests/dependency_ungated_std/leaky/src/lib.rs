@@ -0,0 +1,8 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+mod io_impl;
+mod collections;
@@ -0,0 +1,39 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_unparseable")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_unparseable")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_reports_crates_that_can_not_be_parsed_as_unchecked() {
+    let output = check_output();
+    assert!(output.contains("unparseable: MAYBE"));
+    assert!(output.contains("warning: Could not check the source of the crate: expected `;`"));
+    assert!(output.contains("   --> unparseable/src/lib.rs:4:30"));
+}
+
+#[test]
+fn it_checks_the_rest_of_the_dependency_tree() {
+    let output = check_output();
+    assert!(output.contains("std_only: FAILURE"));
+}
@@ -0,0 +1,6 @@
+#![no_std]
+
+pub fn first(values: &[u8]) -> u8 {
+    let [first, ..] = values else { return 0 };
+    *first
+}
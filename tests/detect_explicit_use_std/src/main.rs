@@ -3,6 +3,4 @@
 use std::ops::Add;
 use std::string::String;
 
-fn main() {
-    println!("Hello, world!");
-}
+fn main() {}
@@ -2,6 +2,4 @@
 
 mod some_module;
 
-fn main() {
-    println!("Hello, world!");
-}
+fn main() {}
@@ -0,0 +1,69 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_succeeds_without_features() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_cfg_gated")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_fails_with_std_feature() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--features")
+        .arg("std")
+        .current_dir("./tests/detect_explicit_use_std_cfg_gated")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_only_reports_compiled_offenses() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--features")
+        .arg("std")
+        .current_dir("./tests/detect_explicit_use_std_cfg_gated")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    let expected_cause = "Source code contains an explicit `use std::` statement";
+    assert_eq!(output.matches(expected_cause).count(), 1);
+    let expected_note = "Ignoring usage of `std::collections::HashMap` in `crate`, as it is compiled out by `cfg(all(feature = \"std\", feature = \"serde\"))`";
+    assert!(output.contains(expected_note));
+}
+
+#[test]
+fn it_respects_gates_on_statements_and_expressions() {
+    let check_output = |args: &[&str]| {
+        let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("check")
+            .args(args)
+            .current_dir("./tests/detect_explicit_use_std_cfg_gated")
+            .output()
+            .unwrap()
+            .stdout;
+        String::from_utf8(output).unwrap()
+    };
+    let expected_cause = "Source code contains a fully qualified `std::` path";
+
+    assert_eq!(check_output(&[]).matches(expected_cause).count(), 0);
+    assert_eq!(
+        check_output(&["--features", "std"])
+            .matches(expected_cause)
+            .count(),
+        3
+    );
+}
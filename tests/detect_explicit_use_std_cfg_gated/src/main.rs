@@ -0,0 +1,23 @@
+#![no_std]
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(all(feature = "std", feature = "serde"))]
+use std::collections::HashMap;
+
+fn main() {
+    #[cfg(feature = "std")]
+    {
+        std::println!("with std");
+    }
+
+    #[cfg(feature = "std")]
+    let _buffer = std::vec::Vec::<u8>::new();
+
+    match 0u8 {
+        #[cfg(feature = "std")]
+        0 => std::process::abort(),
+        _ => {}
+    }
+}
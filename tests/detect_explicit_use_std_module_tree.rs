@@ -0,0 +1,40 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_module_tree")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_module_tree")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_follows_nested_module_declarations() {
+    assert!(check_output().contains("src/parser/lexer.rs"));
+}
+
+#[test]
+fn it_follows_path_attributes() {
+    assert!(check_output().contains("src/backends/host.rs"));
+}
+
+#[test]
+fn it_ignores_files_outside_of_the_module_tree() {
+    assert!(!check_output().contains("src/stale.rs"));
+}
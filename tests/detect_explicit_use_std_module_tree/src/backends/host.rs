@@ -0,0 +1,3 @@
+use std::fs::File;
+
+pub fn open() {}
@@ -0,0 +1,5 @@
+#![no_std]
+
+mod parser;
+#[path = "backends/host.rs"]
+mod host;
@@ -0,0 +1,3 @@
+mod lexer;
+
+pub fn parse() {}
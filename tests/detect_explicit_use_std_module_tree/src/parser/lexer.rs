@@ -0,0 +1,3 @@
+use std::string::String;
+
+pub fn lex() {}
@@ -0,0 +1,39 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_nested")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_nested")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_detects_use_statements_in_methods() {
+    let expected_cause =
+        "Source code contains an explicit `use std::` statement in `crate::parser::Parser::new`";
+    assert!(check_output().contains(expected_cause));
+}
+
+#[test]
+fn it_detects_use_statements_in_inline_modules() {
+    let expected_cause =
+        "Source code contains an explicit `use std::` statement in `crate::parser::cache`";
+    assert!(check_output().contains(expected_cause));
+}
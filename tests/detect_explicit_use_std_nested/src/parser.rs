@@ -0,0 +1,13 @@
+pub struct Parser;
+
+impl Parser {
+    pub fn new() -> Self {
+        use std::collections::HashMap;
+
+        Parser
+    }
+}
+
+mod cache {
+    use std::vec::Vec;
+}
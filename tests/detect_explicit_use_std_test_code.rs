@@ -0,0 +1,44 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_ignores_test_code() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_test_code")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_fails_with_include_tests() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--include-tests")
+        .current_dir("./tests/detect_explicit_use_std_test_code")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_reports_test_code_with_include_tests() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--include-tests")
+        .current_dir("./tests/detect_explicit_use_std_test_code")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    let expected_cause = "Source code contains an explicit `use std::` statement";
+    assert_eq!(output.matches(expected_cause).count(), 2);
+    let expected_cause =
+        "Source code contains a fully qualified `std::` path in `crate::it_adds_zero`";
+    assert!(output.contains(expected_cause));
+}
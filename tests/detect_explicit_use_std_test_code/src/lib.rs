@@ -0,0 +1,25 @@
+#![no_std]
+
+#[cfg(test)]
+mod tests;
+
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[cfg(test)]
+mod inline_tests {
+    use std::vec::Vec;
+
+    #[test]
+    fn it_adds() {
+        let sums: Vec<u32> = (0..3).map(|n| super::add(n, n)).collect();
+        assert_eq!(sums.len(), 3);
+    }
+}
+
+#[test]
+fn it_adds_zero() {
+    let sum = std::cmp::max(crate::add(0, 0), 0);
+    assert_eq!(sum, 0);
+}
@@ -0,0 +1,6 @@
+use std::string::ToString;
+
+#[test]
+fn it_formats() {
+    assert_eq!(super::add(1, 1).to_string(), "2");
+}
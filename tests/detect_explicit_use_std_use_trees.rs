@@ -0,0 +1,29 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_use_trees")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_reports_every_std_leaf() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std_use_trees")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    let expected_cause = "Source code contains an explicit `use std::` statement";
+    assert_eq!(output.matches(expected_cause).count(), 5);
+}
@@ -0,0 +1,8 @@
+#![no_std]
+
+use {core::fmt, std::fs};
+use ::std::io;
+use std as standard;
+use std::{collections::HashMap, vec::Vec};
+
+fn main() {}
@@ -0,0 +1,49 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_std_macros")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_std_macros")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_reports_std_macros() {
+    let output = check_output();
+    assert!(output.contains("std-only macro `println!` in `crate::greet`"));
+    assert!(output.contains("std-only macro `eprintln!` in `crate::greet`"));
+    assert!(output.contains("std-only macro `thread_local!` in `crate`"));
+    assert!(output.contains("Try replacing `println!` with `core::writeln!`"));
+}
+
+#[test]
+fn it_ignores_macros_defined_by_the_crate() {
+    let output = check_output();
+    assert!(!output.contains("`print!`"));
+}
+
+#[test]
+fn it_reports_std_paths_in_macro_arguments() {
+    let output = check_output();
+    let expected_cause = "Source code contains a fully qualified `std::` path";
+    // `std::f32::consts::PI` and the two paths in `thread_local!`, but not the one in the body
+    // of `macro_rules! print`
+    assert_eq!(output.matches(expected_cause).count(), 3);
+}
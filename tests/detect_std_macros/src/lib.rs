@@ -0,0 +1,18 @@
+#[macro_use]
+mod serial {
+    macro_rules! print {
+        ($($arg:tt)*) => {{
+            let _ = ::std::format_args!($($arg)*);
+        }};
+    }
+}
+
+thread_local! {
+    static COUNTER: std::cell::Cell<u32> = std::cell::Cell::new(0);
+}
+
+pub fn greet(name: &str) {
+    println!("Hello {}", name);
+    eprintln!("{}", std::f32::consts::PI);
+    print!("{}", name);
+}
@@ -0,0 +1,8 @@
+#![no_std]
+
+use std::{fmt, ops::Add};
+use std::{fs, mem};
+
+pub fn first() -> std::option::Option<u8> {
+    None
+}
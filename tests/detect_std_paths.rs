@@ -0,0 +1,54 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output() -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_std_paths")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_std_paths")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_prints_cause() {
+    let output = check_output();
+
+    let expected_cause = "Source code contains a fully qualified `std::` path";
+    assert_eq!(output.matches(expected_cause).count(), 3);
+}
+
+#[test]
+fn it_prints_source_line_with_replacement() {
+    let output = check_output();
+    let expected_snippet = "   --> src/lib.rs:12:5
+    |
+12  |    ::std::mem::swap(a, b)
+    |    ^^^^^^^^^^^^^^^^
+help: Try replacing `std::mem::swap` with `core::mem::swap`.
+";
+    assert!(output.contains(expected_snippet));
+}
+
+#[test]
+fn it_underlines_first_line_of_paths_spanning_several_lines() {
+    let output = check_output();
+    let expected_snippet = "15  |pub type Cache = std::collections::HashMap<
+    |                 ^^^^^^^^^^^^^^^^^^^^^^^^^^
+";
+    assert!(output.contains(expected_snippet));
+}
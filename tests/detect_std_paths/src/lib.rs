@@ -0,0 +1,18 @@
+#![no_std]
+
+pub struct Error;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("error")
+    }
+}
+
+pub fn swap(a: &mut u8, b: &mut u8) {
+    ::std::mem::swap(a, b)
+}
+
+pub type Cache = std::collections::HashMap<
+    u8,
+    u8,
+>;
@@ -0,0 +1,141 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn explain_output(code: &str) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("explain")
+        .arg(code)
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_explains_lint_code() {
+    let output = explain_output("NONO0002");
+    assert!(output.contains("use std::ops::Add;"));
+    assert!(output.contains("use core::ops::Add;"));
+}
+
+#[test]
+fn it_explains_lint_name() {
+    assert_eq!(
+        explain_output("missing_no_std_attribute"),
+        explain_output("NONO0001")
+    );
+}
+
+#[test]
+fn it_fails_for_unknown_code() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("explain")
+        .arg("NONO9999")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_prints_error_codes_in_check() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(
+        output.contains("error[NONO0002]: Source code contains an explicit `use std::` statement")
+    );
+    assert!(output.contains("try `cargo nono explain NONO0002`"));
+}
+
+#[test]
+fn it_prints_location_of_offenses() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_explicit_use_std")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+
+    // `use std::ops::Add;`
+    assert!(output.contains("   --> src/main.rs:3:5\n"));
+    // crate-level findings point to the start of the crate root
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/detect_std_macros")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("error[NONO0001]"));
+    assert!(output.contains("   --> src/lib.rs:1:1\n"));
+}
+
+/// Checks a crate whose root is the first `rust` code block of the explanation, i.e. the
+/// erroneous code example.
+fn check_erroneous_example(code: &str) -> String {
+    let explanation = explain_output(code);
+    let example = explanation
+        .split("```rust\n")
+        .nth(1)
+        .and_then(|n| n.split("```").next())
+        .unwrap();
+
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("example_{}", code));
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"example\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )
+    .unwrap();
+    fs::write(crate_dir.join("src/lib.rs"), example).unwrap();
+
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir(&crate_dir)
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_reports_code_for_erroneous_examples() {
+    for code in &["NONO0001", "NONO0002", "NONO0003", "NONO0004", "NONO0006"] {
+        let output = check_erroneous_example(code);
+        assert!(
+            output.contains(&format!("error[{}]", code)),
+            "{} not reported for its example:\n{}",
+            code,
+            output
+        );
+    }
+}
+
+#[test]
+fn it_reports_code_for_default_std_feature() {
+    // the example of NONO0005 needs a dependency from crates.io
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/dependency_default_std")
+        .output()
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("error[NONO0005]"));
+}
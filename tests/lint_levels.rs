@@ -0,0 +1,81 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::{Command, Output};
+
+fn check(args: &[&str]) -> Output {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/lint_levels")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_applies_configured_levels() {
+    let output = check(&[]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("error[NONO0002]"));
+    // missing_no_std_attribute is configured as "warn"
+    assert!(stdout.contains("warning[NONO0001]"));
+    // std_feature is configured as "allow" for the `featured` crate
+    assert!(!stdout.contains("NONO0005"));
+}
+
+#[test]
+fn it_passes_when_denied_lints_are_allowed() {
+    check(&["-A", "use_std_statement"]).assert().code(0);
+    check(&["--allow", "NONO0002"]).assert().code(0);
+}
+
+#[test]
+fn it_applies_later_flags_last() {
+    check(&["-A", "use_std_statement", "-D", "use_std_statement"])
+        .assert()
+        .code(1);
+    check(&["-D", "use_std_statement", "-A", "use_std_statement"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn it_overrides_configured_levels_with_flags() {
+    check(&["-D", "missing_no_std_attribute", "-A", "use_std_statement"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_prefers_crate_levels_over_flags() {
+    check(&["-D", "std_feature", "-A", "use_std_statement"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn it_reports_levels_in_json() {
+    let output = check(&["--message-format", "json"]).stdout;
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert_eq!(json["level"], "deny");
+    let featured = json["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|package| package["name"] == "featured")
+        .unwrap();
+    assert_eq!(featured["level"], "allow");
+}
+
+#[test]
+fn it_fails_for_unknown_lint() {
+    let output = check(&["-A", "foo"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Unknown lint"));
+}
@@ -0,0 +1,3 @@
+#![no_std]
+
+use std::string::String;
@@ -0,0 +1,59 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(args: &[&str]) -> String {
+    // run from a different crate, so that only the manifest path points at the checked one
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/workspace_members/shared_std")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_checks_package_of_manifest() {
+    let output = check_output(&["--manifest-path", "../firmware_c/Cargo.toml"]);
+
+    assert!(output.contains("firmware_c: SUCCESS"));
+    assert!(!output.contains("shared_std"));
+}
+
+#[test]
+fn it_checks_all_members_of_virtual_manifest() {
+    let output = check_output(&["--manifest-path", "../Cargo.toml"]);
+
+    assert!(output.contains("Workspace members:"));
+    assert!(output.contains("2 of 3 workspace members require `std`."));
+}
+
+#[test]
+fn it_checks_selected_package_of_virtual_manifest() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--manifest-path")
+        .arg("../Cargo.toml")
+        .arg("--package")
+        .arg("firmware_c")
+        .current_dir("./tests/workspace_members/shared_std")
+        .assert()
+        .success();
+}
+
+#[test]
+fn it_fails_for_missing_manifest() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--manifest-path")
+        .arg("../missing/Cargo.toml")
+        .current_dir("./tests/workspace_members/shared_std")
+        .assert()
+        .code(1);
+}
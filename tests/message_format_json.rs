@@ -0,0 +1,100 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use serde_json::Value;
+use std::process::Command;
+
+fn check_json(dir: &str) -> Value {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--message-format")
+        .arg("json")
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .stdout;
+    serde_json::from_slice(&output).unwrap()
+}
+
+fn find_package<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["name"] == name)
+        .unwrap()
+}
+
+#[test]
+fn it_fails_with_exit_code_1() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--message-format")
+        .arg("json")
+        .current_dir("./tests/dependency_transitive")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_prints_verdict_and_support() {
+    let report = check_json("./tests/dependency_transitive");
+    assert_eq!(report["tier"], "std");
+    let leaf = find_package(&report, "leaf");
+    assert_eq!(leaf["verdict"], "failure");
+    assert_eq!(leaf["support"]["kind"], "conditional_no_std");
+    assert_eq!(leaf["support"]["condition"], "not(feature = \"std\")");
+    assert_eq!(leaf["support"]["features_to_disable"][0], "std");
+    assert_eq!(
+        leaf["dependency_path"],
+        serde_json::json!(["dependency_transitive:0.1.0", "middle:0.1.0", "leaf:0.1.0"])
+    );
+}
+
+#[test]
+fn it_prints_feature_cause_chain() {
+    let report = check_json("./tests/dependency_transitive");
+    let leaf = find_package(&report, "leaf");
+    let std_feature = &leaf["active_features"][0];
+    assert_eq!(std_feature["name"], "std");
+
+    let cause = &std_feature["causes"][0];
+    assert_eq!(cause["kind"], "feature");
+    assert_eq!(cause["feature"]["name"], "leaf/std");
+    let cause = &cause["feature"]["causes"][0];
+    assert_eq!(cause["kind"], "feature");
+    assert_eq!(cause["feature"]["name"], "std");
+    let cause = &cause["feature"]["causes"][0];
+    assert_eq!(cause["kind"], "explicit");
+    assert!(cause["package_id"]
+        .as_str()
+        .unwrap()
+        .contains("dependency_transitive"));
+}
+
+#[test]
+fn it_prints_offenses_with_location() {
+    let report = check_json("./tests/detect_explicit_use_std");
+    let package = &report["packages"][0];
+    assert_eq!(package["support"]["kind"], "source_offenses");
+    let offense = &package["offenses"][0];
+    assert_eq!(offense["kind"], "use_std_statement");
+    assert_eq!(offense["usage"]["path"], "std::ops::Add");
+    assert_eq!(offense["usage"]["file"], "src/main.rs");
+    assert_eq!(offense["usage"]["line"], 3);
+    assert_eq!(offense["usage"]["column"], 5);
+}
+
+#[test]
+fn it_prints_location_of_parse_errors() {
+    let report = check_json("./tests/dependency_unparseable");
+    let package = find_package(&report, "unparseable");
+    assert_eq!(package["level"], "warn");
+    let support = &package["support"];
+    assert_eq!(support["kind"], "unchecked");
+    assert_eq!(support["file"], "unparseable/src/lib.rs");
+    assert_eq!(support["line"], 4);
+    assert_eq!(support["column"], 30);
+}
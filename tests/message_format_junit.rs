@@ -0,0 +1,40 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(dir: &str) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--message-format")
+        .arg("junit")
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_prints_testcase_per_package() {
+    let output = check_output("./tests/dependency_transitive");
+    assert!(output.starts_with("<?xml"));
+    assert!(output.contains("tests=\"4\" failures=\"1\""));
+    assert!(output.contains("<testcase name=\"middle:0.1.0\" classname=\"cargo-nono\"/>"));
+    assert!(output.contains("<testcase name=\"leaf:0.1.0\" classname=\"cargo-nono\">"));
+}
+
+#[test]
+fn it_prints_feature_causes_in_failure() {
+    let output = check_output("./tests/dependency_transitive");
+    assert!(output.contains("<failure type=\"std\" message=\"leaf requires `std` via dependency_transitive:0.1.0 -&gt; middle:0.1.0 -&gt; leaf:0.1.0\">"));
+    assert!(output
+        .contains("Caused by feature flag &quot;leaf/std&quot; in crate &quot;middle:0.1.0&quot;"));
+}
+
+#[test]
+fn it_prints_offenses_in_failure() {
+    let output = check_output("./tests/detect_explicit_use_std");
+    assert!(output.contains("Source code contains an explicit `use std::` statement"));
+}
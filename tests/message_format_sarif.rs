@@ -0,0 +1,174 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+fn check_sarif(dir: &str) -> Value {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--message-format")
+        .arg("sarif")
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .stdout;
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn it_prints_sarif_log_with_rules() {
+    let sarif = check_sarif("./tests/detect_explicit_use_std");
+    assert_eq!(sarif["version"], "2.1.0");
+    let driver = &sarif["runs"][0]["tool"]["driver"];
+    assert_eq!(driver["name"], "cargo-nono");
+    let rule_ids: Vec<_> = driver["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["id"].as_str().unwrap())
+        .collect();
+    assert!(rule_ids.contains(&"missing_no_std_attribute"));
+    assert!(rule_ids.contains(&"use_std_statement"));
+}
+
+#[test]
+fn it_maps_offenses_to_source_locations() {
+    let sarif = check_sarif("./tests/detect_explicit_use_std");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["ruleId"], "use_std_statement");
+    assert_eq!(results[0]["level"], "error");
+    assert_eq!(
+        results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+        "src/main.rs"
+    );
+}
+
+#[test]
+fn it_suggests_fixes_for_std_paths() {
+    let sarif = check_sarif("./tests/detect_explicit_use_std");
+    let result = &sarif["runs"][0]["results"][0];
+    // `use std::ops::Add;`
+    let region = &result["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(region["startLine"], 3);
+    assert_eq!(region["startColumn"], 5);
+    let change = &result["fixes"][0]["artifactChanges"][0];
+    assert_eq!(change["artifactLocation"]["uri"], "src/main.rs");
+    let replacement = &change["replacements"][0];
+    assert_eq!(replacement["deletedRegion"]["startColumn"], 5);
+    assert_eq!(replacement["deletedRegion"]["endColumn"], 8);
+    assert_eq!(replacement["insertedContent"]["text"], "core");
+}
+
+#[test]
+fn it_only_replaces_std_in_paths_with_generic_arguments() {
+    let sarif = check_sarif("./tests/detect_std_path_fixes");
+    let result = &sarif["runs"][0]["results"][4];
+    // `-> std::option::Option<u8>`, which keeps its generic arguments
+    assert_eq!(result["ruleId"], "std_path");
+    let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+    assert_eq!(replacement["deletedRegion"]["startLine"], 6);
+    assert_eq!(replacement["deletedRegion"]["startColumn"], 19);
+    assert_eq!(replacement["deletedRegion"]["endColumn"], 22);
+    assert_eq!(replacement["insertedContent"]["text"], "core");
+}
+
+#[test]
+fn it_replaces_the_root_of_grouped_use_trees() {
+    let sarif = check_sarif("./tests/detect_std_path_fixes");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    // `use std::{fmt, ops::Add};`
+    for result in &results[0..2] {
+        let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["deletedRegion"]["startLine"], 3);
+        assert_eq!(replacement["deletedRegion"]["startColumn"], 5);
+        assert_eq!(replacement["deletedRegion"]["endColumn"], 8);
+        assert_eq!(replacement["insertedContent"]["text"], "core");
+    }
+    // `use std::{fs, mem};`, as there is no `core::fs`
+    for result in &results[2..4] {
+        assert!(result.get("fixes").is_none());
+    }
+}
+
+#[test]
+fn it_omits_fixes_without_rustup() {
+    // the bin directory of the toolchain contains cargo and rustc, but not rustup
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .unwrap()
+        .stdout;
+    let toolchain_bin = Path::new(String::from_utf8(sysroot).unwrap().trim()).join("bin");
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--message-format")
+        .arg("sarif")
+        .current_dir("./tests/detect_explicit_use_std")
+        .env("PATH", toolchain_bin)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let sarif: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let result = &sarif["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "use_std_statement");
+    assert!(result.get("fixes").is_none());
+}
+
+#[test]
+fn it_maps_dependency_features_to_manifest() {
+    let sarif = check_sarif("./tests/dependency_transitive");
+    let result = &sarif["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "std_feature");
+    let location = &result["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "Cargo.toml");
+    // `middle = { path = "middle", features = ["std"] }`
+    assert_eq!(location["region"]["startLine"], 8);
+}
+
+#[test]
+fn it_maps_default_features_to_features_table() {
+    let sarif = check_sarif("./tests/dependency_default_std");
+    let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "Cargo.toml");
+    // `default = ["serde/std"]`
+    assert_eq!(location["region"]["startLine"], 10);
+}
+
+#[test]
+fn it_maps_dependency_features_to_normal_dependencies() {
+    let sarif = check_sarif("./tests/dependency_dev_and_normal");
+    let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    // `leaf` in `[dependencies]`, not in `[dev-dependencies]`
+    assert_eq!(location["region"]["startLine"], 11);
+}
+
+#[test]
+#[cfg(unix)]
+fn it_maps_dependency_features_to_platform_specific_dependencies() {
+    let sarif = check_sarif("./tests/dependency_target_features");
+    let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "Cargo.toml");
+    // `leaf` in `[target.'cfg(unix)'.dependencies]`, which requests the "std" feature
+    assert_eq!(location["region"]["startLine"], 11);
+}
+
+#[test]
+fn it_reports_parse_errors_as_notifications() {
+    let sarif = check_sarif("./tests/dependency_unparseable");
+    let run = &sarif["runs"][0];
+    let notification = &run["invocations"][0]["toolExecutionNotifications"][0];
+    assert_eq!(notification["level"], "warning");
+    let location = &notification["locations"][0]["physicalLocation"];
+    assert_eq!(
+        location["artifactLocation"]["uri"],
+        "unparseable/src/lib.rs"
+    );
+    assert_eq!(location["region"]["startLine"], 4);
+    // only the findings of `std_only`, as the unchecked crate is not a finding of a rule
+    assert_eq!(run["results"].as_array().unwrap().len(), 2);
+}
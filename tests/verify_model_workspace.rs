@@ -0,0 +1,34 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+#[test]
+fn it_explains_features_unified_by_other_workspace_members() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--verify-model")
+        .current_dir("./tests/verify_model_workspace")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("Feature model matches Cargo's resolve for all 2 crates."));
+}
+
+#[test]
+fn it_succeeds_if_model_matches() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--verify-model")
+        .current_dir("./tests/dependency_feature_syntax")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("Feature model matches Cargo's resolve for all 2 crates."));
+}
@@ -0,0 +1,69 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(dir: &str, args: &[&str]) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_ignores_features_requested_by_other_members() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .current_dir("./tests/workspace_member_features/no_std_member")
+        .assert()
+        .success();
+    let output = check_output("./tests/workspace_member_features/no_std_member", &[]);
+    assert!(output.contains("shared: SUCCESS (core)"));
+}
+
+#[test]
+fn it_keeps_features_requested_by_checked_member() {
+    let output = check_output("./tests/workspace_member_features/std_member", &[]);
+    assert!(output.contains("shared: FAILURE (std)"));
+    // either explicitly or via the default feature
+    assert!(output.contains("feature from \"std_member:0.1.0\""));
+}
+
+#[test]
+fn it_matches_model_with_features_of_other_members() {
+    let output = check_output(
+        "./tests/workspace_member_features/no_std_member",
+        &["--verify-model"],
+    );
+    assert!(output.contains("Feature model matches Cargo's resolve for all 2 crates."));
+}
+
+#[test]
+fn it_checks_shared_dependency_per_feature_set() {
+    let output = check_output("./tests/workspace_member_features", &["--workspace"]);
+
+    assert!(output.contains("shared: SUCCESS (core)"));
+    assert!(output.contains("shared: FAILURE (std)"));
+    assert!(output.contains("Affects workspace members: std_member\n"));
+    assert!(output.contains("no_std_member: SUCCESS (core)"));
+    assert!(output.contains("std_member: FAILURE (std)"));
+}
+
+#[test]
+fn it_ignores_features_of_excluded_members() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--workspace")
+        .arg("--exclude")
+        .arg("std_member")
+        .current_dir("./tests/workspace_member_features")
+        .assert()
+        .success();
+}
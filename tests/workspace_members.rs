@@ -0,0 +1,61 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn check_output(args: &[&str]) -> String {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .args(args)
+        .current_dir("./tests/workspace_members")
+        .output()
+        .unwrap()
+        .stdout;
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn it_fails_for_workspace_with_std_member() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--workspace")
+        .current_dir("./tests/workspace_members")
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn it_checks_shared_dependency_once() {
+    let output = check_output(&["--workspace"]);
+
+    assert_eq!(output.matches("shared_std: FAILURE").count(), 1);
+    assert_eq!(output.matches("shared_core: SUCCESS").count(), 1);
+    assert!(output.contains("Affects workspace members: firmware_a, firmware_b"));
+}
+
+#[test]
+fn it_prints_verdict_per_member() {
+    let output = check_output(&["--workspace"]);
+
+    assert!(output.contains("firmware_a: FAILURE (std)"));
+    assert!(output.contains("firmware_b: FAILURE (std)"));
+    assert!(output.contains("firmware_c: SUCCESS (core)"));
+    assert!(output.contains("2 of 3 workspace members require `std`."));
+}
+
+#[test]
+fn it_succeeds_with_excluded_std_members() {
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("check")
+        .arg("--workspace")
+        .arg("--exclude")
+        .arg("firmware_a")
+        .arg("--exclude")
+        .arg("firmware_b")
+        .current_dir("./tests/workspace_members")
+        .assert()
+        .success();
+}